@@ -11,7 +11,7 @@ use anyhow::{Context, Result};
 use criterion::{
   criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, Criterion,
 };
-use flowistry::infoflow::Direction;
+use flowistry::infoflow::{DependencyKind, Direction};
 use glob::glob;
 use rustc_borrowck::consumers::BodyWithBorrowckFacts;
 use rustc_hir::{BodyId, ItemKind};
@@ -46,7 +46,13 @@ fn analysis<'tcx>(
       })
       .collect::<Vec<_>>();
 
-    flowistry::infoflow::compute_dependencies(&results, targets, Direction::Both);
+    flowistry::infoflow::compute_dependencies(
+      &results,
+      targets,
+      Direction::Both,
+      DependencyKind::All,
+    )
+    .unwrap();
   }
 }
 
@@ -135,7 +141,7 @@ fn criterion_benchmark(c: &mut Criterion) {
     let bench_crate_pattern = curr_dir.join(format!("*libbench_utils*{}", DLL_SUFFIX));
 
     let print_sysroot = Command::new("rustc")
-      .args(&["--print", "sysroot"])
+      .args(["--print", "sysroot"])
       .output()
       .context("Failed to print rustc sysroot")?
       .stdout;
@@ -143,7 +149,7 @@ fn criterion_benchmark(c: &mut Criterion) {
 
     // Find bench_utils .so file
     let shared_object = glob(bench_crate_pattern.to_str().unwrap())?
-      .nth(0)
+      .next()
       .with_context(|| {
         format!(
           "Failed to find bench_utils shared object in dir {}",
@@ -185,7 +191,7 @@ fn criterion_benchmark(c: &mut Criterion) {
     match std::env::var("FLOWISTRY_BENCH_TEST") {
       Ok(test_file) => {
         let test = TESTS
-          .into_iter()
+          .iter()
           .find(|t| t.1 == test_file)
           .with_context(|| format!("Failed to find test file '{test_file}'"))?;
         run_bench(*test);
@@ -24,7 +24,7 @@ extern crate rustc_span;
 
 use std::process::Command;
 
-use flowistry::infoflow::Direction;
+use flowistry::infoflow::{DependencyKind, Direction};
 use rustc_borrowck::consumers::BodyWithBorrowckFacts;
 use rustc_hir::{BodyId, ItemKind};
 use rustc_middle::{
@@ -61,7 +61,9 @@ fn compute_dependencies<'tcx>(
     &results,
     targets.clone(),
     Direction::Forward,
+    DependencyKind::All,
   )
+  .unwrap()
   .remove(0);
 
   // And print out those forward dependencies. Note that while each location has an
@@ -128,7 +130,7 @@ fn main() {
 
   // Get the sysroot so rustc can find libstd
   let print_sysroot = Command::new("rustc")
-    .args(&["--print", "sysroot"])
+    .args(["--print", "sysroot"])
     .output()
     .unwrap()
     .stdout;
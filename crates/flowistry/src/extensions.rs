@@ -1,9 +1,8 @@
 //! Extra features for evaluating / ablating the precision of Flowistry's algorithm.
 #![allow(missing_docs)]
 
-use std::{cell::RefCell, str::FromStr};
+use std::str::FromStr;
 
-use fluid_let::fluid_let;
 use serde::{Deserialize, Serialize};
 
 /// Whether Flowistry should ignore the distinction between mutable and immtuable references
@@ -46,6 +45,48 @@ impl FromStr for ContextMode {
   }
 }
 
+/// Whether Flowistry should recurse into call sites of functions defined in other crates
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, Hash)]
+pub enum CrossCrateMode {
+  /// Only recurse into functions in the local crate
+  LocalOnly,
+  /// Also recurse into functions whose MIR is available from another crate's metadata,
+  /// approximating their borrowck facts conservatively
+  Recurse,
+}
+
+impl FromStr for CrossCrateMode {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "LocalOnly" => Ok(Self::LocalOnly),
+      "Recurse" => Ok(Self::Recurse),
+      _ => Err(format!("Could not parse: {s}")),
+    }
+  }
+}
+
+/// Whether Flowistry should recurse into call sites of functions that contain `unsafe` code
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, Hash)]
+pub enum UnsafeMode {
+  /// Only use the modular approximation for functions containing `unsafe` code
+  Skip,
+  /// Also recurse into functions containing `unsafe` code, assuming that raw pointers
+  /// can alias any place that is borrowed in the function
+  Recurse,
+}
+
+impl FromStr for UnsafeMode {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "Skip" => Ok(Self::Skip),
+      "Recurse" => Ok(Self::Recurse),
+      _ => Err(format!("Could not parse: {s}")),
+    }
+  }
+}
+
 /// Whether Flowistry should use lifetimes to distinguish pointers
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, Hash)]
 pub enum PointerMode {
@@ -72,6 +113,8 @@ pub struct EvalMode {
   pub mutability_mode: MutabilityMode,
   pub context_mode: ContextMode,
   pub pointer_mode: PointerMode,
+  pub cross_crate_mode: CrossCrateMode,
+  pub unsafe_mode: UnsafeMode,
 }
 
 impl Default for EvalMode {
@@ -80,13 +123,8 @@ impl Default for EvalMode {
       mutability_mode: MutabilityMode::DistinguishMut,
       context_mode: ContextMode::SigOnly,
       pointer_mode: PointerMode::Precise,
+      cross_crate_mode: CrossCrateMode::LocalOnly,
+      unsafe_mode: UnsafeMode::Skip,
     }
   }
 }
-
-fluid_let!(pub static EVAL_MODE: EvalMode);
-fluid_let!(pub static REACHED_LIBRARY: RefCell<bool>);
-
-pub fn is_extension_active(f: impl Fn(EvalMode) -> bool) -> bool {
-  EVAL_MODE.copied().map(f).unwrap_or(false)
-}
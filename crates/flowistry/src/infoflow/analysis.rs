@@ -1,19 +1,22 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, iter, ops::Deref, rc::Rc};
 
 use indexical::impls::RustcIndexMatrix as IndexMatrix;
 use log::{debug, trace};
-use rustc_data_structures::fx::FxHashMap as HashMap;
-use rustc_hir::{def_id::DefId, BodyId};
+use rustc_data_structures::{
+  fx::{FxHashMap as HashMap, FxHashSet as HashSet},
+  sync::Lrc,
+};
+use rustc_hir::def_id::DefId;
 use rustc_middle::{
   mir::{visit::Visitor, *},
   ty::TyCtxt,
 };
-use rustc_mir_dataflow::{Analysis, AnalysisDomain};
+use rustc_mir_dataflow::{Analysis, AnalysisDomain, JoinSemiLattice};
 use rustc_utils::{
   mir::{
     control_dependencies::ControlDependencies,
     location_or_arg::{
-      index::{LocationOrArgDomain, LocationOrArgSet},
+      index::{LocationOrArgDomain, LocationOrArgIndex, LocationOrArgSet},
       LocationOrArg,
     },
   },
@@ -23,14 +26,19 @@ use smallvec::SmallVec;
 
 use super::{
   mutation::{ModularMutationVisitor, Mutation, MutationStatus},
-  FlowResults,
+  recursive::{CallKey, CalleeSummary, FlowCache, Instantiation, SummaryTable},
+  DependencyKind, FlowConfig, FlowDiagnostics, TrackedKinds, UntrackedKind,
 };
 use crate::{
-  extensions::{is_extension_active, ContextMode, MutabilityMode},
-  mir::placeinfo::PlaceInfo,
+  extensions::{ContextMode, MutabilityMode},
+  mir::{
+    budget::DomainSize,
+    engine::{DiffDomain, StatementEffect},
+    placeinfo::PlaceInfo,
+  },
 };
 
-/// Represents the information flows at a given instruction. See [`FlowResults`] for a high-level explanation of this datatype.
+/// Represents the information flows at a given instruction. See [`FlowResults`](super::FlowResults) for a high-level explanation of this datatype.
 ///
 /// `FlowDomain` represents $\Theta$ that maps from places $p$ to dependencies $\kappa$. To efficiently represent $\kappa$, a set of locations,
 /// we use the bit-set data structures in [`rustc_index::bit_set`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_index/bit_set/index.html).
@@ -55,7 +63,207 @@ use crate::{
 /// information flow analysis: an instruction `bb[0]: _2 = _1` (where `_1` is an argument) would set $\Theta(\verb|_2|) = \Theta(\verb|_1|) \cup \\{\verb|bb0\[0\]|\\}\$.
 /// However, $\Theta(\verb|_1|)$ would be empty, so it would be imposible to determine that `_2` depends on `_1`. To solve this issue, we
 /// enrich the domain of locations with arguments, using the [`LocationOrArg`] type. Any dependency can be on *either* a location or an argument.
-pub type FlowDomain<'tcx> = IndexMatrix<Place<'tcx>, LocationOrArg>;
+///
+/// # **Note:** data and control dependencies
+/// `FlowDomain` dereferences to the [`IndexMatrix`] of *all* dependencies, i.e. both data and control dependencies.
+/// If enabled by [`FlowConfig::tracked_kinds`], it also tracks a matrix of only the data dependencies, i.e. the
+/// dependencies that do not go through the condition of a branch, and a matrix of the dependencies of a thin slice,
+/// i.e. data dependencies that also do not go through the pointers used to access a place. Use
+/// [`FlowAnalysis::deps_for_kind`](crate::infoflow::FlowAnalysis::deps_for_kind) to read either kind of dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowDomain<'tcx> {
+  deps: IndexMatrix<Place<'tcx>, LocationOrArg>,
+  data_deps: Option<IndexMatrix<Place<'tcx>, LocationOrArg>>,
+  thin_deps: Option<IndexMatrix<Place<'tcx>, LocationOrArg>>,
+}
+
+impl<'tcx> FlowDomain<'tcx> {
+  /// Creates an empty `FlowDomain` over the given locations that tracks the given kinds of dependencies.
+  pub fn new(
+    location_domain: &Rc<LocationOrArgDomain>,
+    tracked_kinds: TrackedKinds,
+  ) -> Self {
+    let matrix = |tracked: bool| tracked.then(|| IndexMatrix::new(location_domain));
+    FlowDomain {
+      deps: IndexMatrix::new(location_domain),
+      data_deps: matrix(tracked_kinds.data),
+      thin_deps: matrix(tracked_kinds.thin),
+    }
+  }
+
+  /// Returns the kinds of dependencies tracked by this domain.
+  pub fn tracked_kinds(&self) -> TrackedKinds {
+    TrackedKinds {
+      data: self.data_deps.is_some(),
+      thin: self.thin_deps.is_some(),
+    }
+  }
+
+  /// Returns the matrix of all dependencies.
+  pub fn deps(&self) -> &IndexMatrix<Place<'tcx>, LocationOrArg> {
+    &self.deps
+  }
+
+  /// Returns the matrix of data dependencies, if they are tracked.
+  pub fn data_deps(&self) -> Option<&IndexMatrix<Place<'tcx>, LocationOrArg>> {
+    self.data_deps.as_ref()
+  }
+
+  /// Returns the matrix of thin slice dependencies, if they are tracked.
+  pub fn thin_deps(&self) -> Option<&IndexMatrix<Place<'tcx>, LocationOrArg>> {
+    self.thin_deps.as_ref()
+  }
+
+  /// Returns the matrix of dependencies of the given kind, which cannot be [`DependencyKind::Control`].
+  ///
+  /// # Panics
+  /// If the kind is not tracked by this domain.
+  pub(crate) fn matrix(
+    &self,
+    kind: DependencyKind,
+  ) -> &IndexMatrix<Place<'tcx>, LocationOrArg> {
+    let matrix = match kind {
+      DependencyKind::All => Some(&self.deps),
+      DependencyKind::Data => self.data_deps.as_ref(),
+      DependencyKind::Thin => self.thin_deps.as_ref(),
+      DependencyKind::Control => unreachable!("control dependencies are not stored"),
+    };
+    matrix.unwrap_or_else(|| {
+      panic!("{kind:?} dependencies are not tracked, see FlowConfig::tracked_kinds")
+    })
+  }
+
+  fn matrix_mut(
+    &mut self,
+    kind: DependencyKind,
+  ) -> Option<&mut IndexMatrix<Place<'tcx>, LocationOrArg>> {
+    match kind {
+      DependencyKind::All => Some(&mut self.deps),
+      DependencyKind::Data => self.data_deps.as_mut(),
+      DependencyKind::Thin => self.thin_deps.as_mut(),
+      DependencyKind::Control => unreachable!("control dependencies are not stored"),
+    }
+  }
+
+  /// Returns the tracked matrices with their kinds.
+  fn matrices(
+    &self,
+  ) -> impl Iterator<Item = (DependencyKind, &IndexMatrix<Place<'tcx>, LocationOrArg>)>
+  {
+    [
+      (DependencyKind::All, Some(&self.deps)),
+      (DependencyKind::Data, self.data_deps.as_ref()),
+      (DependencyKind::Thin, self.thin_deps.as_ref()),
+    ]
+    .into_iter()
+    .filter_map(|(kind, matrix)| Some((kind, matrix?)))
+  }
+
+  /// Returns the tracked matrices, for updates that apply to every kind.
+  fn matrices_mut(
+    &mut self,
+  ) -> impl Iterator<Item = &mut IndexMatrix<Place<'tcx>, LocationOrArg>> {
+    iter::once(&mut self.deps)
+      .chain(self.data_deps.as_mut())
+      .chain(self.thin_deps.as_mut())
+  }
+}
+
+impl<'tcx> Deref for FlowDomain<'tcx> {
+  type Target = IndexMatrix<Place<'tcx>, LocationOrArg>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.deps
+  }
+}
+
+impl<'tcx> JoinSemiLattice for FlowDomain<'tcx> {
+  fn join(&mut self, other: &Self) -> bool {
+    let join = |matrix: Option<&mut IndexMatrix<_, _>>,
+                other: Option<&IndexMatrix<_, _>>| {
+      match (matrix, other) {
+        (Some(matrix), Some(other)) => matrix.join(other),
+        _ => false,
+      }
+    };
+    let changed = self.deps.join(&other.deps);
+    let changed = join(self.data_deps.as_mut(), other.data_deps.as_ref()) || changed;
+    join(self.thin_deps.as_mut(), other.thin_deps.as_ref()) || changed
+  }
+}
+
+impl DomainSize for FlowDomain<'_> {
+  fn domain_size(&self) -> usize {
+    self
+      .matrices()
+      .map(|(_, matrix)| matrix.domain_size())
+      .sum()
+  }
+}
+
+impl DomainSize for IndexMatrix<Place<'_>, LocationOrArg> {
+  fn domain_size(&self) -> usize {
+    self.rows().count()
+  }
+}
+
+/// The rows that differ between two matrices of dependencies, for
+/// [`EngineMode::RowDeltas`](crate::mir::engine::EngineMode::RowDeltas).
+///
+/// Each row is `None` if it was removed.
+pub type MatrixDiff<'tcx> = Vec<(Place<'tcx>, Option<LocationOrArgSet>)>;
+
+impl<'tcx> DiffDomain for IndexMatrix<Place<'tcx>, LocationOrArg> {
+  type Diff = MatrixDiff<'tcx>;
+
+  fn diff(&self, prev: &Self) -> Self::Diff {
+    let mut diff = Vec::new();
+    let prev_rows = prev.rows().collect::<HashMap<_, _>>();
+    for (place, row) in self.rows() {
+      if prev_rows.get(place) != Some(&row) {
+        diff.push((*place, Some(row.clone())));
+      }
+    }
+    let rows = self.rows().map(|(place, _)| place).collect::<HashSet<_>>();
+    for place in prev_rows.keys() {
+      if !rows.contains(place) {
+        diff.push((**place, None));
+      }
+    }
+    diff
+  }
+
+  fn apply_diff(&mut self, diff: &Self::Diff) {
+    for (place, row) in diff {
+      self.clear_row(place);
+      if let Some(row) = row {
+        self.union_into_row(*place, row);
+      }
+    }
+  }
+}
+
+/// The rows that differ between two [`FlowDomain`]s, for each tracked kind of dependency.
+pub type FlowDomainDiff<'tcx> = Vec<(DependencyKind, MatrixDiff<'tcx>)>;
+
+impl<'tcx> DiffDomain for FlowDomain<'tcx> {
+  type Diff = FlowDomainDiff<'tcx>;
+
+  fn diff(&self, prev: &Self) -> Self::Diff {
+    self
+      .matrices()
+      .map(|(kind, matrix)| (kind, matrix.diff(prev.matrix(kind))))
+      .collect()
+  }
+
+  fn apply_diff(&mut self, diff: &Self::Diff) {
+    for (kind, diff) in diff {
+      if let Some(matrix) = self.matrix_mut(*kind) {
+        matrix.apply_diff(diff);
+      }
+    }
+  }
+}
 
 /// Data structure that holds context for performing the information flow analysis.
 pub struct FlowAnalysis<'tcx> {
@@ -72,7 +280,11 @@ pub struct FlowAnalysis<'tcx> {
   pub place_info: PlaceInfo<'tcx>,
 
   pub(crate) control_dependencies: ControlDependencies<BasicBlock>,
-  pub(crate) recurse_cache: RefCell<HashMap<BodyId, FlowResults<'tcx>>>,
+  pub(crate) recurse_cache: RefCell<HashMap<CallKey<'tcx>, CalleeSummary<'tcx>>>,
+  pub(crate) summaries: Rc<RefCell<SummaryTable<'tcx>>>,
+  pub(crate) cache: Lrc<FlowCache<'tcx>>,
+  pub(crate) instantiation: Option<Instantiation<'tcx>>,
+  pub(crate) diagnostics: RefCell<FlowDiagnostics>,
 }
 
 impl<'tcx> FlowAnalysis<'tcx> {
@@ -93,15 +305,34 @@ impl<'tcx> FlowAnalysis<'tcx> {
       place_info,
       control_dependencies,
       recurse_cache,
+      summaries: Rc::default(),
+      cache: Lrc::default(),
+      instantiation: None,
+      diagnostics: RefCell::default(),
     }
   }
 
+  /// Returns the configuration of the analysis.
+  pub fn config(&self) -> &FlowConfig {
+    self.place_info.config()
+  }
+
+  /// Returns the diagnostics collected while computing these results, including those
+  /// of every callee that was recursed into.
+  pub fn diagnostics(&self) -> FlowDiagnostics {
+    let mut diagnostics = self.diagnostics.borrow().clone();
+    diagnostics
+      .unsafe_callees
+      .sort_by_key(|def_id| self.tcx.def_path_str(*def_id));
+    diagnostics
+  }
+
   /// Returns the [`LocationOrArgDomain`] used by the analysis.
   pub fn location_domain(&self) -> &Rc<LocationOrArgDomain> {
     self.place_info.location_domain()
   }
 
-  fn provenance(&self, place: Place<'tcx>) -> SmallVec<[Place<'tcx>; 8]> {
+  pub(crate) fn provenance(&self, place: Place<'tcx>) -> SmallVec<[Place<'tcx>; 8]> {
     place
       .refs_in_projection()
       .flat_map(|(place_ref, _)| {
@@ -113,7 +344,7 @@ impl<'tcx> FlowAnalysis<'tcx> {
       .collect()
   }
 
-  fn influences(&self, place: Place<'tcx>) -> SmallVec<[Place<'tcx>; 8]> {
+  pub(crate) fn influences(&self, place: Place<'tcx>) -> SmallVec<[Place<'tcx>; 8]> {
     let conflicts = self.place_info.aliases(place).iter().copied();
     conflicts
       .chain(self.provenance(place))
@@ -122,6 +353,51 @@ impl<'tcx> FlowAnalysis<'tcx> {
       .collect()
   }
 
+  /// Like [`FlowAnalysis::influences`], but excluding the pointers used to access `place`.
+  pub(crate) fn thin_influences(&self, place: Place<'tcx>) -> SmallVec<[Place<'tcx>; 8]> {
+    self
+      .place_info
+      .aliases(place)
+      .iter()
+      .flat_map(|alias| self.place_info.conflicts(*alias))
+      .copied()
+      .collect()
+  }
+
+  /// Returns true if `mutation` definitely overwrites its target, i.e. the
+  /// previous dependencies of the mutated place can be discarded.
+  ///
+  /// If the alias analysis exceeded its budget, then the alias of a place behind a
+  /// reference may be coarser than the place, so only direct places are strongly updated.
+  pub(crate) fn is_strong_update(&self, mutation: &Mutation<'tcx>) -> bool {
+    matches!(mutation.status, MutationStatus::Definitely)
+      && self.place_info.aliases(mutation.mutated).len() == 1
+      && (!self.place_info.is_degraded() || mutation.mutated.is_direct(self.body))
+  }
+
+  /// Returns the aliases of `mutated` that can actually be written through it.
+  pub(crate) fn mutable_aliases(
+    &self,
+    mutated: Place<'tcx>,
+  ) -> SmallVec<[Place<'tcx>; 8]> {
+    let ignore_mut = self.config().eval_mode.mutability_mode == MutabilityMode::IgnoreMut;
+    self
+      .place_info
+      .aliases(mutated)
+      .iter()
+      .filter(|alias| {
+        // Remove any conflicts that aren't actually mutable, e.g. if x : &T ends up
+        // as an alias of y: &mut T. See test function_lifetime_alias_mut for an example.
+        let has_immut = alias.iter_projections().any(|(sub_place, _)| {
+          let ty = sub_place.ty(self.body.local_decls(), self.tcx).ty;
+          matches!(ty.ref_mutability(), Some(Mutability::Not))
+        });
+        !has_immut || ignore_mut
+      })
+      .copied()
+      .collect()
+  }
+
   /// Returns all the dependencies of `place` within `state`.
   ///
   /// Prefer using this method instead of accessing `FlowDomain` directly,
@@ -131,14 +407,49 @@ impl<'tcx> FlowAnalysis<'tcx> {
     state: &FlowDomain<'tcx>,
     place: Place<'tcx>,
   ) -> LocationOrArgSet {
+    self.tracked_deps_for_kind(state, place, DependencyKind::All)
+  }
+
+  /// Returns the dependencies of `place` within `state` of the given kind,
+  /// or an error if `state` does not track that kind.
+  pub fn deps_for_kind(
+    &self,
+    state: &FlowDomain<'tcx>,
+    place: Place<'tcx>,
+    kind: DependencyKind,
+  ) -> Result<LocationOrArgSet, UntrackedKind> {
+    if !state.tracked_kinds().contains(kind) {
+      return Err(UntrackedKind(kind));
+    }
+    Ok(self.tracked_deps_for_kind(state, place, kind))
+  }
+
+  /// Like [`FlowAnalysis::deps_for_kind`], but panics if `state` does not track `kind`.
+  pub(crate) fn tracked_deps_for_kind(
+    &self,
+    state: &FlowDomain<'tcx>,
+    place: Place<'tcx>,
+    kind: DependencyKind,
+  ) -> LocationOrArgSet {
+    if kind == DependencyKind::Control {
+      let mut deps = self.tracked_deps_for_kind(state, place, DependencyKind::All);
+      deps.subtract(&self.tracked_deps_for_kind(state, place, DependencyKind::Data));
+      return deps;
+    }
+
+    let matrix = state.matrix(kind);
+    let influences = |place| match kind {
+      DependencyKind::Thin => self.thin_influences(place),
+      _ => self.influences(place),
+    };
     let mut deps = LocationOrArgSet::new(self.location_domain());
     for subplace in self
       .place_info
       .reachable_values(place, Mutability::Not)
       .iter()
-      .flat_map(|place| self.influences(*place))
+      .flat_map(|place| influences(*place))
     {
-      deps.union(state.row_set(&self.place_info.normalize(subplace)));
+      deps.union(matrix.row_set(&self.place_info.normalize(subplace)));
     }
     deps
   }
@@ -154,29 +465,50 @@ impl<'tcx> FlowAnalysis<'tcx> {
     let location_domain = self.location_domain();
 
     // Initialize dependencies to include current location of mutation.
-    let mut all_deps = {
+    let initial_deps = {
       let mut deps = LocationOrArgSet::new(location_domain);
       deps.insert(location);
-      vec![deps; mutations.len()]
+      deps
     };
 
     // Add every influence on `input` to `deps`.
-    let add_deps = |state: &FlowDomain<'tcx>,
-                    input,
+    let add_deps = |matrix: &IndexMatrix<Place<'tcx>, LocationOrArg>,
+                    influences: SmallVec<[Place<'tcx>; 8]>,
                     target_deps: &mut LocationOrArgSet| {
-      for relevant in self.influences(input) {
-        let relevant_deps = state.row_set(&self.place_info.normalize(relevant));
-        trace!("    For relevant {relevant:?} for input {input:?} adding deps {relevant_deps:?}");
+      for relevant in influences {
+        let relevant_deps = matrix.row_set(&self.place_info.normalize(relevant));
+        trace!("    For relevant {relevant:?} adding deps {relevant_deps:?}");
         target_deps.union(relevant_deps);
       }
     };
 
+    // Compute the dependencies of the inputs of each mutation within `matrix`.
+    let input_deps =
+      |matrix: &IndexMatrix<Place<'tcx>, LocationOrArg>,
+       influences: &dyn Fn(Place<'tcx>) -> SmallVec<[Place<'tcx>; 8]>| {
+        mutations
+          .iter()
+          .map(|mt| {
+            let mut deps = initial_deps.clone();
+            for input in &mt.inputs {
+              add_deps(matrix, influences(*input), &mut deps);
+            }
+            deps
+          })
+          .collect::<Vec<_>>()
+      };
+
+    // Data dependencies are tracked in parallel, excluding control dependencies.
+    // Thin dependencies additionally exclude the provenance of inputs and mutated places.
+    let mut all_data_deps = state
+      .data_deps()
+      .map(|matrix| input_deps(matrix, &|place| self.influences(place)));
+    let all_thin_deps = state
+      .thin_deps()
+      .map(|matrix| input_deps(matrix, &|place| self.thin_influences(place)));
+
     // Register every explicitly provided input as an input.
-    for (mt, deps) in mutations.iter().zip(&mut all_deps) {
-      for input in &mt.inputs {
-        add_deps(state, *input, deps);
-      }
-    }
+    let mut all_deps = input_deps(state.deps(), &|place| self.influences(place));
 
     // Add location of every control dependency.
     let controlled_by = self.control_dependencies.dependent_on(location.block);
@@ -191,54 +523,71 @@ impl<'tcx> FlowAnalysis<'tcx> {
       if let TerminatorKind::SwitchInt { discr, .. } = &terminator.kind {
         if let Some(discr_place) = discr.as_place() {
           for deps in &mut all_deps {
-            add_deps(state, discr_place, deps);
+            add_deps(state.deps(), self.influences(discr_place), deps);
           }
         }
       }
     }
 
-    let ignore_mut =
-      is_extension_active(|mode| mode.mutability_mode == MutabilityMode::IgnoreMut);
-    for (mt, deps) in mutations.iter().zip(&mut all_deps) {
+    for (i, mt) in mutations.iter().enumerate() {
       // Clear sub-places of mutated place (if sound to do so)
-      if matches!(mt.status, MutationStatus::Definitely)
-        && self.place_info.aliases(mt.mutated).len() == 1
-      {
+      if self.is_strong_update(mt) {
         for sub in self.place_info.children(mt.mutated).iter() {
-          state.clear_row(&self.place_info.normalize(*sub));
+          let sub = self.place_info.normalize(*sub);
+          for matrix in state.matrices_mut() {
+            matrix.clear_row(&sub);
+          }
         }
       }
 
       // If mutated place is indirect, add deps of provenance
       for place in self.provenance(mt.mutated) {
         for conflict in self.place_info.conflicts(place) {
-          deps.union(state.row_set(&self.place_info.normalize(*conflict)));
+          let conflict = self.place_info.normalize(*conflict);
+          all_deps[i].union(state.deps.row_set(&conflict));
+          if let (Some(data_deps), Some(matrix)) = (&mut all_data_deps, &state.data_deps)
+          {
+            data_deps[i].union(matrix.row_set(&conflict));
+          }
         }
       }
 
-      let mutable_aliases = self
-        .place_info
-        .aliases(mt.mutated)
-        .iter()
-        .filter(|alias| {
-          // Remove any conflicts that aren't actually mutable, e.g. if x : &T ends up
-          // as an alias of y: &mut T. See test function_lifetime_alias_mut for an example.
-          let has_immut = alias.iter_projections().any(|(sub_place, _)| {
-            let ty = sub_place.ty(body.local_decls(), self.tcx).ty;
-            matches!(ty.ref_mutability(), Some(Mutability::Not))
-          });
-          !has_immut || ignore_mut
-        })
-        .collect::<SmallVec<[_; 8]>>();
+      let mutable_aliases = self.mutable_aliases(mt.mutated);
 
       debug!("  Mutated places: {mutable_aliases:?}");
-      debug!("    with deps {deps:?}");
-
-      for alias in mutable_aliases {
-        state.union_into_row(self.place_info.normalize(*alias), deps);
+      debug!("    with deps {:?}", all_deps[i]);
+
+      let tracked = [
+        (Some(&mut state.deps), Some(&all_deps)),
+        (state.data_deps.as_mut(), all_data_deps.as_ref()),
+        (state.thin_deps.as_mut(), all_thin_deps.as_ref()),
+      ];
+      for (matrix, deps) in tracked {
+        let (Some(matrix), Some(deps)) = (matrix, deps) else {
+          continue;
+        };
+        for alias in &mutable_aliases {
+          matrix.union_into_row(self.place_info.normalize(*alias), &deps[i]);
+        }
       }
     }
   }
+
+  /// Returns the rows that every place starts with, i.e. each argument (and its conflicts)
+  /// depends on the argument.
+  pub(crate) fn initial_rows(
+    &self,
+  ) -> impl Iterator<Item = (Place<'tcx>, LocationOrArgIndex)> + '_ {
+    self.place_info.all_args().flat_map(move |(arg, loc)| {
+      self.place_info.conflicts(arg).iter().map(move |place| {
+        debug!(
+          "arg={arg:?} / place={place:?} / loc={:?}",
+          self.location_domain().value(loc)
+        );
+        (self.place_info.normalize(*place), loc)
+      })
+    })
+  }
 }
 
 impl<'tcx> AnalysisDomain<'tcx> for FlowAnalysis<'tcx> {
@@ -246,25 +595,21 @@ impl<'tcx> AnalysisDomain<'tcx> for FlowAnalysis<'tcx> {
   const NAME: &'static str = "FlowAnalysis";
 
   fn bottom_value(&self, _body: &Body<'tcx>) -> Self::Domain {
-    FlowDomain::new(self.location_domain())
+    FlowDomain::new(self.location_domain(), self.config().tracked_kinds)
   }
 
   fn initialize_start_block(&self, _body: &Body<'tcx>, state: &mut Self::Domain) {
-    for (arg, loc) in self.place_info.all_args() {
-      for place in self.place_info.conflicts(arg) {
-        debug!(
-          "arg={arg:?} / place={place:?} / loc={:?}",
-          self.location_domain().value(loc)
-        );
-        state.insert(self.place_info.normalize(*place), loc);
+    for (place, loc) in self.initial_rows() {
+      for matrix in state.matrices_mut() {
+        matrix.insert(place, loc);
       }
     }
   }
 }
 
-impl<'tcx> Analysis<'tcx> for FlowAnalysis<'tcx> {
-  fn apply_statement_effect(
-    &mut self,
+impl<'tcx> StatementEffect<'tcx> for FlowAnalysis<'tcx> {
+  fn statement_effect(
+    &self,
     state: &mut Self::Domain,
     statement: &Statement<'tcx>,
     location: Location,
@@ -274,6 +619,17 @@ impl<'tcx> Analysis<'tcx> for FlowAnalysis<'tcx> {
     })
     .visit_statement(statement, location);
   }
+}
+
+impl<'tcx> Analysis<'tcx> for FlowAnalysis<'tcx> {
+  fn apply_statement_effect(
+    &mut self,
+    state: &mut Self::Domain,
+    statement: &Statement<'tcx>,
+    location: Location,
+  ) {
+    self.statement_effect(state, statement, location);
+  }
 
   fn apply_terminator_effect<'mir>(
     &mut self,
@@ -282,7 +638,7 @@ impl<'tcx> Analysis<'tcx> for FlowAnalysis<'tcx> {
     location: Location,
   ) -> TerminatorEdges<'mir, 'tcx> {
     if matches!(terminator.kind, TerminatorKind::Call { .. })
-      && is_extension_active(|mode| mode.context_mode == ContextMode::Recurse)
+      && self.config().eval_mode.context_mode == ContextMode::Recurse
       && self.recurse_into_call(state, &terminator.kind, location)
     {
       return terminator.edges();
@@ -0,0 +1,82 @@
+//! Programmatic models of the information flow through calls.
+//!
+//! A [`CallModel`] computes the [`Mutation`]s performed by a call, which replace the
+//! default handling of that call (recursing into the callee, [summaries](super::summaries),
+//! or the [modular approximation](super::mutation::ModularMutationVisitor)).
+//! This is useful for calls whose effects cannot be expressed as a static summary, such as
+//! RPC stubs that route arguments by name, or APIs whose effects depend on generic parameters.
+//!
+//! Models are only used if they are included in the [`FlowConfig`](super::FlowConfig)
+//! of the analysis.
+
+use std::sync::Arc;
+
+use rustc_middle::{
+  mir::{Location, TerminatorKind},
+  ty::{Instance, TyKind},
+};
+
+use super::mutation::Mutation;
+use crate::mir::placeinfo::PlaceInfo;
+
+/// A model of the information flow through some calls.
+pub trait CallModel<'tcx> {
+  /// Returns the mutations performed by `call` at `location`, or `None` to decline to model
+  /// the call so that the default handling applies.
+  ///
+  /// `call` is always a [`TerminatorKind::Call`], and `place_info` describes the places
+  /// in the caller. If the callee is a known function, then `callee` is the instance
+  /// it resolves to in the caller.
+  fn model_call(
+    &self,
+    call: &TerminatorKind<'tcx>,
+    callee: Option<Instance<'tcx>>,
+    place_info: &PlaceInfo<'tcx>,
+    location: Location,
+  ) -> Option<Vec<Mutation<'tcx>>>;
+}
+
+/// A set of [`CallModel`]s, consulted in the order they were registered.
+#[derive(Clone, Default)]
+pub struct CallModels(Vec<Arc<dyn for<'tcx> CallModel<'tcx> + Send + Sync>>);
+
+impl CallModels {
+  /// Adds `model` after all previously registered models.
+  pub fn register(
+    &mut self,
+    model: impl for<'tcx> CallModel<'tcx> + Send + Sync + 'static,
+  ) {
+    self.0.push(Arc::new(model));
+  }
+
+  /// Returns the mutations computed by the first model that accepts `call`.
+  pub fn model_call<'tcx>(
+    &self,
+    call: &TerminatorKind<'tcx>,
+    place_info: &PlaceInfo<'tcx>,
+    location: Location,
+  ) -> Option<Vec<Mutation<'tcx>>> {
+    if self.0.is_empty() {
+      return None;
+    }
+
+    let TerminatorKind::Call { func, .. } = call else {
+      return None;
+    };
+    let tcx = place_info.tcx;
+    let callee = match func.constant().map(|func| func.const_.ty().kind()) {
+      Some(TyKind::FnDef(def_id, args)) => {
+        let param_env = tcx.param_env(place_info.def_id);
+        Instance::resolve(tcx, param_env, *def_id, args)
+          .ok()
+          .flatten()
+      }
+      _ => None,
+    };
+
+    self
+      .0
+      .iter()
+      .find_map(|model| model.model_call(call, callee, place_info, location))
+  }
+}
@@ -0,0 +1,148 @@
+//! Configuration and diagnostics of the information flow analysis.
+
+use std::fmt;
+
+use rustc_hir::def_id::DefId;
+
+use super::{call_model::CallModels, summaries::CallSummaries, DependencyKind};
+use crate::{
+  extensions::EvalMode,
+  mir::{budget::Budget, engine::EngineMode},
+};
+
+/// Configuration of the information flow analysis, passed to
+/// [`compute_flow_with_config`](super::compute_flow_with_config).
+///
+/// The default configuration only uses the modular approximation for calls,
+/// without any summaries or call models.
+#[derive(Clone, Default)]
+pub struct FlowConfig {
+  /// The precision levers of the analysis.
+  pub eval_mode: EvalMode,
+
+  /// Summaries of library functions, see [`summaries`](super::summaries).
+  pub summaries: CallSummaries,
+
+  /// Programmatic models of calls, see [`call_model`](super::call_model).
+  pub call_models: CallModels,
+
+  /// How the results of the analysis are stored, trading time for memory.
+  pub engine_mode: EngineMode,
+
+  /// Limits on the resources used by the analysis, which is unlimited by default.
+  pub budget: Budget,
+
+  /// The kinds of dependencies tracked besides [`DependencyKind::All`].
+  pub tracked_kinds: TrackedKinds,
+}
+
+impl FlowConfig {
+  /// Creates a configuration with the given precision levers and no summaries or call models.
+  pub fn new(eval_mode: EvalMode) -> Self {
+    FlowConfig {
+      eval_mode,
+      ..Default::default()
+    }
+  }
+}
+
+/// The kinds of dependencies that a [`FlowDomain`](super::FlowDomain) tracks.
+///
+/// Dependencies of [`DependencyKind::All`] are always tracked. Every other kind adds a
+/// matrix to each `FlowDomain`, so by default no other kind is tracked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TrackedKinds {
+  /// Whether data dependencies are tracked, which are also needed for control dependencies.
+  pub data: bool,
+
+  /// Whether thin slice dependencies are tracked.
+  pub thin: bool,
+}
+
+impl TrackedKinds {
+  /// Tracks every kind of dependency.
+  pub fn all() -> Self {
+    TrackedKinds {
+      data: true,
+      thin: true,
+    }
+  }
+
+  /// Tracks only what is needed to read dependencies of the given `kind`.
+  pub fn for_kind(kind: DependencyKind) -> Self {
+    TrackedKinds {
+      data: matches!(kind, DependencyKind::Data | DependencyKind::Control),
+      thin: kind == DependencyKind::Thin,
+    }
+  }
+
+  /// Returns true if dependencies of the given `kind` can be read.
+  pub fn contains(&self, kind: DependencyKind) -> bool {
+    match kind {
+      DependencyKind::All => true,
+      DependencyKind::Data | DependencyKind::Control => self.data,
+      DependencyKind::Thin => self.thin,
+    }
+  }
+}
+
+/// The error returned when reading dependencies of a kind that the analysis did not track,
+/// see [`FlowConfig::tracked_kinds`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UntrackedKind(pub DependencyKind);
+
+impl fmt::Display for UntrackedKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{:?} dependencies are not tracked, see FlowConfig::tracked_kinds",
+      self.0
+    )
+  }
+}
+
+impl std::error::Error for UntrackedKind {}
+
+/// Facts about how the results of the analysis were computed, e.g. where they rely on
+/// approximations. Returned by [`FlowAnalysis::diagnostics`](super::FlowAnalysis::diagnostics).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlowDiagnostics {
+  /// True if a call could not be recursed into because its callee is defined in
+  /// another crate (and its MIR is not available or not usable).
+  pub reached_library: bool,
+
+  /// The functions containing `unsafe` code that were recursed into (directly or
+  /// transitively), sorted by path.
+  ///
+  /// Recursing into such functions is only enabled by
+  /// [`UnsafeMode::Recurse`](crate::extensions::UnsafeMode::Recurse), and
+  /// the analysis of raw pointers within them is conservative, so results for these
+  /// functions are less trustworthy than for safe code.
+  pub unsafe_callees: Vec<DefId>,
+
+  /// True if the analysis, its alias analysis, or the analysis of a callee exceeded its
+  /// [`Budget`] and returned an over-approximation.
+  ///
+  /// The dependencies computed from degraded results are still sound, but may include
+  /// many more locations than the actual dependencies. See
+  /// [`AnalysisResults::degraded_locations`](crate::mir::engine::AnalysisResults::degraded_locations)
+  /// for the locations where the information flow analysis did not finish.
+  pub degraded: bool,
+}
+
+impl FlowDiagnostics {
+  /// Adds the diagnostics of a callee analysis to these diagnostics.
+  pub(crate) fn join(&mut self, other: &FlowDiagnostics) {
+    self.reached_library |= other.reached_library;
+    self.degraded |= other.degraded;
+    for def_id in &other.unsafe_callees {
+      self.add_unsafe_callee(*def_id);
+    }
+  }
+
+  pub(crate) fn add_unsafe_callee(&mut self, def_id: DefId) {
+    if !self.unsafe_callees.contains(&def_id) {
+      self.unsafe_callees.push(def_id);
+    }
+  }
+}
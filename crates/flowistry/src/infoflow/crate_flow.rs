@@ -0,0 +1,84 @@
+//! Information flow for every body in a crate.
+
+use std::rc::Rc;
+
+use rustc_data_structures::{
+  fx::FxHashMap as HashMap,
+  marker::{DynSend, DynSync},
+  sync::{IntoDynSyncSend, Lock, Lrc},
+};
+use rustc_hir::{def_id::LocalDefId, BodyId};
+use rustc_middle::ty::TyCtxt;
+use rustc_query_system::query::QueryCache;
+
+use super::{recursive::FlowCache, FlowConfig, FlowResults};
+
+/// Computes information flow for every body in the local crate, and calls `f` with the
+/// results of each body.
+///
+/// Bodies are scheduled on rustc's thread pool, so they are analyzed in parallel when
+/// rustc is started with `-Z threads=N` for `N > 1`, and one at a time otherwise.
+/// Summaries of callees are shared by the analyses of all bodies, so a function called
+/// from many bodies is only analyzed once (unless it is part of a recursive cycle).
+///
+/// Each body is borrow-checked by the first thread that needs it, either to analyze the body
+/// or to recurse into it, and is then shared with the other threads. Since
+/// [`get_body_with_borrowck_facts`](rustc_utils::mir::borrowck_facts::get_body_with_borrowck_facts)
+/// only provides a body on the thread that borrow-checked it, this function must be called
+/// before rustc borrow-checks the crate itself.
+///
+/// Results cannot leave the thread that computed them, so `f` is called on that thread,
+/// and its outputs are returned in the order of
+/// [`Map::body_owners`](rustc_middle::hir::map::Map::body_owners).
+///
+/// # Panics
+/// If rustc already borrow-checked a body of the crate, e.g. when called after the analysis
+/// phase or a second time in the same compilation.
+pub fn compute_crate_flow<'tcx, T: DynSend>(
+  tcx: TyCtxt<'tcx>,
+  config: &FlowConfig,
+  f: impl Fn(BodyId, &FlowResults<'tcx>) -> T + DynSend + DynSync,
+) -> Vec<(LocalDefId, T)> {
+  let body_owners = tcx.hir().body_owners().collect::<Vec<_>>();
+  let borrow_checked = body_owners.iter().find(|def_id| {
+    tcx
+      .query_system
+      .caches
+      .mir_borrowck
+      .lookup(def_id)
+      .is_some()
+  });
+  if let Some(def_id) = borrow_checked {
+    panic!(
+      "compute_crate_flow must be called before rustc borrow-checks the crate, \
+       but {} was already borrow-checked",
+      tcx.def_path_str(*def_id)
+    );
+  }
+
+  let cache = Lrc::new(FlowCache::for_crate(tcx));
+  cache.borrowck_side_effects(tcx);
+
+  let config = IntoDynSyncSend(config);
+  let outputs = Lock::new(HashMap::default());
+  tcx.hir().par_body_owners(|def_id| {
+    let body_id = tcx.hir().body_owned_by(def_id);
+    let results = super::compute_flow_for_input(
+      tcx,
+      def_id.to_def_id(),
+      cache.body(tcx, def_id),
+      Rc::new(FlowConfig::clone(&config)),
+      Rc::default(),
+      cache.clone(),
+      None,
+    );
+    let output = f(body_id, &results);
+    outputs.lock().insert(def_id, output);
+  });
+
+  let mut outputs = outputs.into_inner();
+  body_owners
+    .into_iter()
+    .map(|def_id| (def_id, outputs.remove(&def_id).unwrap()))
+    .collect()
+}
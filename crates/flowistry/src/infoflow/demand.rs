@@ -0,0 +1,299 @@
+//! Demand-driven computation of backward dependencies.
+//!
+//! [`compute_dependencies`](super::compute_dependencies) reads the dependencies of a place from the [`FlowDomain`](super::FlowDomain)
+//! at its location, which requires computing the dependencies of every place at every location.
+//! [`compute_backward_dependencies`] instead starts from the rows of the queried places and walks
+//! backward through the [`Mutation`]s and control dependencies that the
+//! [`FlowAnalysis`] would use to compute those rows, so it only visits the
+//! places and locations that are relevant to the query.
+
+use std::{iter, rc::Rc};
+
+use log::{debug, info};
+use rustc_borrowck::consumers::BodyWithBorrowckFacts;
+use rustc_data_structures::fx::{FxHashMap as HashMap, FxHashSet as HashSet};
+use rustc_hir::BodyId;
+use rustc_index::bit_set::BitSet;
+use rustc_middle::{
+  mir::{visit::Visitor, *},
+  ty::TyCtxt,
+};
+use rustc_utils::{
+  block_timer,
+  mir::location_or_arg::{index::LocationOrArgSet, LocationOrArg},
+  OperandExt,
+};
+use smallvec::SmallVec;
+
+use super::{
+  dependencies::compute_tracked_dependencies,
+  mutation::{ModularMutationVisitor, Mutation},
+  DependencyKind, Direction, FlowAnalysis, FlowConfig, TrackedKinds,
+};
+use crate::{extensions::ContextMode, mir::placeinfo::PlaceInfo};
+
+/// Walks backward from the rows of a [`FlowDomain`](super::FlowDomain) to the locations
+/// that the rows depend on, for one kind of dependency.
+struct DemandSlicer<'a, 'tcx> {
+  analysis: &'a FlowAnalysis<'tcx>,
+  kind: DependencyKind,
+  reachable: &'a BitSet<BasicBlock>,
+  /// The mutations at each visited location, grouped by transfer function like
+  /// they are applied by the [`FlowAnalysis`].
+  mutations: HashMap<Location, Vec<Vec<Mutation<'tcx>>>>,
+}
+
+impl<'a, 'tcx> DemandSlicer<'a, 'tcx> {
+  /// Returns the places that influence `place` when reading its rows, like
+  /// [`FlowAnalysis::deps_for_kind`] does.
+  fn influences(&self, place: Place<'tcx>) -> SmallVec<[Place<'tcx>; 8]> {
+    match self.kind {
+      DependencyKind::Thin => self.analysis.thin_influences(place),
+      _ => self.analysis.influences(place),
+    }
+  }
+
+  fn normalize(&self, place: Place<'tcx>) -> Place<'tcx> {
+    self.analysis.place_info.normalize(place)
+  }
+
+  /// Returns the rows that the dependencies of `place` are read from.
+  fn target_rows(&self, place: Place<'tcx>) -> HashSet<Place<'tcx>> {
+    self
+      .analysis
+      .place_info
+      .reachable_values(place, Mutability::Not)
+      .iter()
+      .flat_map(|place| self.influences(*place))
+      .map(|place| self.normalize(place))
+      .collect()
+  }
+
+  /// Given the rows that are needed after `location`, adds the locations they depend on
+  /// at `location` to `deps` and returns the rows they depend on before `location`.
+  ///
+  /// This inverts [`FlowAnalysis::transfer_function`]: mutations are visited from last
+  /// to first, and a strong update stops the walk for the rows it clears.
+  fn rows_before(
+    &mut self,
+    location: Location,
+    mut rows: HashSet<Place<'tcx>>,
+    deps: &mut LocationOrArgSet,
+  ) -> HashSet<Place<'tcx>> {
+    let analysis = self.analysis;
+    let place_info = &analysis.place_info;
+    let body = analysis.body;
+    self.mutations.entry(location).or_insert_with(|| {
+      let mut mutations = Vec::new();
+      ModularMutationVisitor::new(place_info, |_, mts| mutations.push(mts))
+        .visit_location(body, location);
+      mutations
+    });
+
+    let normalize = |place| place_info.normalize(place);
+    for mts in self.mutations[&location].iter().rev() {
+      // Inputs are read from the state before all of the mutations of a transfer function
+      let mut input_rows = HashSet::default();
+      for mt in mts.iter().rev() {
+        let written = analysis
+          .mutable_aliases(mt.mutated)
+          .into_iter()
+          .any(|alias| rows.contains(&normalize(alias)));
+
+        if written {
+          deps.insert(location);
+          for input in &mt.inputs {
+            input_rows.extend(self.influences(*input).into_iter().map(normalize));
+          }
+
+          if self.kind == DependencyKind::All {
+            let controlled_by =
+              analysis.control_dependencies.dependent_on(location.block);
+            for block in controlled_by.into_iter().flat_map(|set| set.iter()) {
+              deps.insert(body.terminator_loc(block));
+              let terminator = body.basic_blocks[block].terminator();
+              if let TerminatorKind::SwitchInt { discr, .. } = &terminator.kind {
+                if let Some(discr_place) = discr.as_place() {
+                  input_rows
+                    .extend(analysis.influences(discr_place).into_iter().map(normalize));
+                }
+              }
+            }
+          }
+
+          // The provenance is read after the mutated place is cleared
+          if self.kind != DependencyKind::Thin {
+            for place in analysis.provenance(mt.mutated) {
+              rows.extend(place_info.conflicts(place).iter().map(|c| normalize(*c)));
+            }
+          }
+        }
+
+        if analysis.is_strong_update(mt) {
+          for sub in place_info.children(mt.mutated).iter() {
+            rows.remove(&normalize(*sub));
+          }
+        }
+      }
+      rows.extend(input_rows);
+    }
+
+    rows
+  }
+
+  /// Returns the locations that the `targets` depend on.
+  fn dependencies(
+    &mut self,
+    targets: &[(Place<'tcx>, LocationOrArg)],
+  ) -> LocationOrArgSet {
+    let analysis = self.analysis;
+    let body = analysis.body;
+    let mut deps = LocationOrArgSet::new(analysis.location_domain());
+
+    // The rows that are needed after each location
+    let mut visited: HashMap<Location, HashSet<Place<'tcx>>> = HashMap::default();
+    let mut stack = Vec::new();
+    for (place, location) in targets {
+      match location {
+        LocationOrArg::Arg(..) => {
+          deps.insert(*location);
+        }
+        LocationOrArg::Location(location) => {
+          stack.push((*location, self.target_rows(*place)));
+        }
+      }
+    }
+
+    let budget = &analysis.place_info.config.budget;
+    let mut iterations = 0;
+    let mut domain_size = 0;
+    while let Some((location, rows)) = stack.pop() {
+      // Like a fixpoint that exceeds its budget, the walk then assumes that the targets
+      // depend on everything
+      if budget.is_exceeded(iterations, domain_size) {
+        info!("Demand-driven dependencies exceeded their budget");
+        deps.insert_all();
+        break;
+      }
+      iterations += 1;
+
+      // Locations that the analysis does not reach only have empty rows
+      if !self.reachable.contains(location.block) {
+        continue;
+      }
+
+      let visited = visited.entry(location).or_default();
+      let rows = rows
+        .into_iter()
+        .filter(|row| visited.insert(*row))
+        .collect::<HashSet<_>>();
+      domain_size = domain_size.max(visited.len());
+      if rows.is_empty() {
+        continue;
+      }
+
+      let rows = self.rows_before(location, rows, &mut deps);
+      if rows.is_empty() {
+        continue;
+      }
+
+      if location.statement_index > 0 {
+        let prev = Location {
+          block: location.block,
+          statement_index: location.statement_index - 1,
+        };
+        stack.push((prev, rows));
+        continue;
+      }
+
+      if location.block == START_BLOCK {
+        for (arg, index) in analysis.place_info.all_args() {
+          let conflicts = analysis.place_info.conflicts(arg);
+          if conflicts
+            .iter()
+            .any(|place| rows.contains(&self.normalize(*place)))
+          {
+            deps.insert(index);
+          }
+        }
+      }
+
+      for pred in &body.basic_blocks.predecessors()[location.block] {
+        stack.push((body.terminator_loc(*pred), rows.clone()));
+      }
+    }
+
+    deps
+  }
+}
+
+/// Computes the backward dependencies of places at locations, without computing the
+/// [`FlowResults`](super::FlowResults) of the body.
+///
+/// The output is the same as that of [`compute_dependencies`](super::compute_dependencies) with [`Direction::Backward`]
+/// on the results of [`compute_flow_with_config`](super::compute_flow_with_config), but only
+/// the places and locations that the targets depend on are visited. This is faster for a
+/// few queries on a large body, while computing the full results is faster for many queries.
+///
+/// Walking backward through calls is not supported with [`ContextMode::Recurse`], in which
+/// case this function computes the full results.
+///
+/// The walk for each group of targets is limited by the [`Budget`](crate::mir::budget::Budget)
+/// of `config`, where each visited location counts as one iteration. If the walk exceeds its
+/// budget, then the targets are assumed to depend on every location and argument.
+pub fn compute_backward_dependencies<'tcx>(
+  tcx: TyCtxt<'tcx>,
+  body_id: BodyId,
+  body_with_facts: &'tcx BodyWithBorrowckFacts<'tcx>,
+  config: &FlowConfig,
+  all_targets: Vec<Vec<(Place<'tcx>, LocationOrArg)>>,
+  kind: DependencyKind,
+) -> Vec<LocationOrArgSet> {
+  if config.eval_mode.context_mode == ContextMode::Recurse {
+    let config = FlowConfig {
+      tracked_kinds: TrackedKinds::for_kind(kind),
+      ..config.clone()
+    };
+    let results = super::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
+    return compute_tracked_dependencies(
+      &results,
+      all_targets,
+      Direction::Backward,
+      kind,
+    );
+  }
+
+  block_timer!("compute_backward_dependencies");
+  let def_id = tcx.hir().body_owner_def_id(body_id).to_def_id();
+  let body = &body_with_facts.body;
+  let place_info =
+    PlaceInfo::build_with_config(tcx, def_id, body_with_facts, Rc::new(config.clone()));
+  let analysis = FlowAnalysis::new(tcx, def_id, body, place_info);
+  let reachable = traversal::reachable_as_bitset(body);
+
+  let dependencies = |kind| {
+    let mut slicer = DemandSlicer {
+      analysis: &analysis,
+      kind,
+      reachable: &reachable,
+      mutations: HashMap::default(),
+    };
+    all_targets
+      .iter()
+      .map(|targets| slicer.dependencies(targets))
+      .collect::<Vec<_>>()
+  };
+
+  let deps = if kind == DependencyKind::Control {
+    let data_deps = dependencies(DependencyKind::Data);
+    let mut all_deps = dependencies(DependencyKind::All);
+    for (deps, data_deps) in iter::zip(&mut all_deps, &data_deps) {
+      deps.subtract(data_deps);
+    }
+    all_deps
+  } else {
+    dependencies(kind)
+  };
+  debug!("deps={deps:?}");
+  deps
+}
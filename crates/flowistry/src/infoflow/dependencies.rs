@@ -1,4 +1,4 @@
-use std::{cell::RefCell, iter};
+use std::{cell::RefCell, iter, str::FromStr};
 
 use either::Either;
 use log::{debug, trace};
@@ -10,9 +10,10 @@ use rustc_utils::{
   source_map::spanner::{EnclosingHirSpans, Spanner},
   BodyExt, OperandExt, SpanExt,
 };
+use serde::{Deserialize, Serialize};
 
-use super::{mutation::ModularMutationVisitor, FlowDomain, FlowResults};
-use crate::{infoflow::mutation::Mutation, mir::placeinfo::PlaceInfo};
+use super::{mutation::ModularMutationVisitor, FlowResults, UntrackedKind};
+use crate::infoflow::mutation::Mutation;
 
 /// Which way to look for dependencies
 #[derive(Clone, Copy, Debug)]
@@ -27,6 +28,41 @@ pub enum Direction {
   Both,
 }
 
+/// Which kinds of dependencies to include
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DependencyKind {
+  /// Dependencies through the data used by an instruction, e.g. `y` in `x = y + 1`
+  Data,
+
+  /// Dependencies only through the condition of a branch, e.g. `y` in `if y { x = 1 }`.
+  ///
+  /// A location that is also a data dependency is not a control dependency,
+  /// e.g. `y` in `x = y; if y { x = 1 }` is only a [`DependencyKind::Data`] dependency of `x`.
+  Control,
+
+  /// Both data and control dependencies
+  All,
+
+  /// Only the instructions that produced a value through data flow, i.e. a thin slice.
+  ///
+  /// Unlike [`DependencyKind::Data`], this excludes the instructions that computed the
+  /// pointers through which a value is read or written, e.g. `p = &x` in `*p = y`.
+  Thin,
+}
+
+impl FromStr for DependencyKind {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "Data" => Ok(Self::Data),
+      "Control" => Ok(Self::Control),
+      "All" => Ok(Self::All),
+      "Thin" => Ok(Self::Thin),
+      _ => Err(format!("Could not parse: {s}")),
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 struct TargetDeps {
   all_forward: Vec<LocationOrArgSet>,
@@ -36,6 +72,7 @@ impl TargetDeps {
   pub fn new<'tcx>(
     targets: &[(Place<'tcx>, LocationOrArg)],
     results: &FlowResults<'tcx>,
+    kind: DependencyKind,
   ) -> Self {
     let place_info = &results.analysis.place_info;
     let location_domain = results.analysis.location_domain();
@@ -54,7 +91,8 @@ impl TargetDeps {
           LocationOrArg::Arg(..) => Location::START,
           LocationOrArg::Location(location) => location,
         };
-        let state = results.state_at(state_location);
+        let state = results.shared_state_at(state_location);
+        let state = state.matrix(kind);
         // backward.union(&aliases.deps(state, place));
 
         let mut forward = LocationOrArgSet::new(location_domain);
@@ -79,14 +117,6 @@ impl TargetDeps {
   }
 }
 
-pub fn deps<'a, 'tcx>(
-  state: &'a FlowDomain<'tcx>,
-  place_info: &'a PlaceInfo<'tcx>,
-  place: Place<'tcx>,
-) -> &'a LocationOrArgSet {
-  state.row_set(&place_info.normalize(place))
-}
-
 /// Computes the dependencies of a place $p$ at a location $\ell$ in a given
 /// direction.
 ///
@@ -96,11 +126,59 @@ pub fn deps<'a, 'tcx>(
 /// For efficiency reasons, this function actually takes a list of list of places at locations.
 /// For example, if `all_targets = [[x@L1, y@L2], [z@L3]]` then the result would be
 /// `[deps(x@L1) ∪ deps(y@L2), deps(z@L3)]`.
+///
+/// The `kind` determines whether the dependencies are through data, control, or both,
+/// see [`DependencyKind`]. Control-only dependencies are those that are dependencies of any kind,
+/// but not data dependencies. If the `kind` is not tracked by the analysis, see
+/// [`FlowConfig::tracked_kinds`](super::FlowConfig::tracked_kinds), then this returns an error.
+///
+/// If the analysis exceeded its budget, then the dependencies are over-approximated at the
+/// [degraded locations](crate::mir::engine::AnalysisResults::degraded_locations):
+/// a place at such a location depends on every location, and such a location depends on every place.
 pub fn compute_dependencies<'tcx>(
   results: &FlowResults<'tcx>,
   all_targets: Vec<Vec<(Place<'tcx>, LocationOrArg)>>,
   direction: Direction,
+  kind: DependencyKind,
+) -> Result<Vec<LocationOrArgSet>, UntrackedKind> {
+  if !results.analysis.config().tracked_kinds.contains(kind) {
+    return Err(UntrackedKind(kind));
+  }
+  Ok(compute_tracked_dependencies(
+    results,
+    all_targets,
+    direction,
+    kind,
+  ))
+}
+
+/// Like [`compute_dependencies`], but panics if the `kind` is not tracked by the analysis.
+pub(crate) fn compute_tracked_dependencies<'tcx>(
+  results: &FlowResults<'tcx>,
+  all_targets: Vec<Vec<(Place<'tcx>, LocationOrArg)>>,
+  direction: Direction,
+  kind: DependencyKind,
 ) -> Vec<LocationOrArgSet> {
+  if kind == DependencyKind::Control {
+    let data_deps = compute_tracked_dependencies(
+      results,
+      all_targets.clone(),
+      direction,
+      DependencyKind::Data,
+    );
+    let mut all_deps = compute_tracked_dependencies(
+      results,
+      all_targets.clone(),
+      direction,
+      DependencyKind::All,
+    );
+    for (deps, data_deps) in iter::zip(&mut all_deps, &data_deps) {
+      deps.subtract(data_deps);
+    }
+    add_degraded_dependencies(results, &all_targets, direction, &mut all_deps);
+    return all_deps;
+  }
+
   block_timer!("compute_dependencies");
   log::info!("Computing dependencies for {} targets", all_targets.len());
   debug!("all_targets={all_targets:#?}");
@@ -119,7 +197,7 @@ pub fn compute_dependencies<'tcx>(
   let forward = || {
     let all_target_deps = all_targets
       .iter()
-      .map(|targets| TargetDeps::new(targets, results))
+      .map(|targets| TargetDeps::new(targets, results, kind))
       .collect::<Vec<_>>();
     log::info!(
       "sub-targets: {}",
@@ -146,9 +224,10 @@ pub fn compute_dependencies<'tcx>(
     }
 
     for location in body.all_locations() {
-      let state = results.state_at(location);
+      let state = results.shared_state_at(location);
+      let state = state.matrix(kind);
       let check = |place| {
-        let deps = deps(state, aliases, place);
+        let deps = state.row_set(&aliases.normalize(place));
 
         for (target_deps, outputs) in
           iter::zip(&all_target_deps, &mut *outputs.borrow_mut())
@@ -190,9 +269,11 @@ pub fn compute_dependencies<'tcx>(
             outputs.insert(*location);
           }
           LocationOrArg::Location(location) => {
-            let deps = results
-              .analysis
-              .deps_for(results.state_at(*location), *place);
+            let deps = results.analysis.tracked_deps_for_kind(
+              &results.shared_state_at(*location),
+              *place,
+              kind,
+            );
             outputs.union(&deps);
           }
         }
@@ -209,7 +290,82 @@ pub fn compute_dependencies<'tcx>(
     }
   };
 
-  outputs.into_inner()
+  let mut outputs = outputs.into_inner();
+  add_degraded_dependencies(results, &all_targets, direction, &mut outputs);
+  outputs
+}
+
+/// Adds the dependencies that [`compute_dependencies`] cannot rule out at the locations
+/// where the analysis exceeded its budget.
+fn add_degraded_dependencies<'tcx>(
+  results: &FlowResults<'tcx>,
+  all_targets: &[Vec<(Place<'tcx>, LocationOrArg)>],
+  direction: Direction,
+  outputs: &mut [LocationOrArgSet],
+) {
+  let Some(degraded) = results.degraded_locations() else {
+    return;
+  };
+
+  for (targets, outputs) in iter::zip(all_targets, outputs) {
+    if matches!(direction, Direction::Forward | Direction::Both) {
+      outputs.union(degraded);
+    }
+
+    let target_degraded = targets
+      .iter()
+      .any(|(_, location)| degraded.contains(*location));
+    if matches!(direction, Direction::Backward | Direction::Both) && target_degraded {
+      outputs.insert_all();
+    }
+  }
+}
+
+/// Computes the chop between `sources` and `sinks`, i.e. the locations that lie on some
+/// flow from a source place at a location to a sink place at a location.
+///
+/// The chop is the intersection of the forward dependencies of the sources with the
+/// backward dependencies of the sinks.
+pub fn compute_chop<'tcx>(
+  results: &FlowResults<'tcx>,
+  sources: Vec<(Place<'tcx>, LocationOrArg)>,
+  sinks: Vec<(Place<'tcx>, LocationOrArg)>,
+) -> LocationOrArgSet {
+  let mut chop = compute_tracked_dependencies(
+    results,
+    vec![sources],
+    Direction::Forward,
+    DependencyKind::All,
+  )
+  .remove(0);
+  let backward = compute_tracked_dependencies(
+    results,
+    vec![sinks],
+    Direction::Backward,
+    DependencyKind::All,
+  )
+  .remove(0);
+  chop.intersect(&backward);
+  debug!("chop={chop:?}");
+  chop
+}
+
+fn location_spans(
+  results: &FlowResults<'_>,
+  deps: &LocationOrArgSet,
+  spanner: &Spanner,
+) -> Vec<Span> {
+  let body = results.analysis.body;
+  let location_spans = deps
+    .iter()
+    .flat_map(|location| {
+      spanner.location_to_spans(*location, body, EnclosingHirSpans::OuterOnly)
+    })
+    .collect::<Vec<_>>();
+
+  let merged_spans = Span::merge_overlaps(location_spans);
+  trace!("Spans: {merged_spans:?}");
+  merged_spans
 }
 
 /// Wraps [`compute_dependencies`] by translating each [`Location`] to a corresponding
@@ -219,25 +375,27 @@ pub fn compute_dependency_spans<'tcx>(
   targets: Vec<Vec<(Place<'tcx>, LocationOrArg)>>,
   direction: Direction,
   spanner: &Spanner,
-) -> Vec<Vec<Span>> {
-  let body = results.analysis.body;
-
-  let all_deps = compute_dependencies(results, targets, direction);
+  kind: DependencyKind,
+) -> Result<Vec<Vec<Span>>, UntrackedKind> {
+  let all_deps = compute_dependencies(results, targets, direction, kind)?;
   debug!("all_deps={all_deps:?}");
 
-  all_deps
-    .into_iter()
-    .map(|deps| {
-      let location_spans = deps
-        .iter()
-        .flat_map(|location| {
-          spanner.location_to_spans(*location, body, EnclosingHirSpans::OuterOnly)
-        })
-        .collect::<Vec<_>>();
+  Ok(
+    all_deps
+      .iter()
+      .map(|deps| location_spans(results, deps, spanner))
+      .collect::<Vec<_>>(),
+  )
+}
 
-      let merged_spans = Span::merge_overlaps(location_spans);
-      trace!("Spans: {merged_spans:?}");
-      merged_spans
-    })
-    .collect::<Vec<_>>()
+/// Wraps [`compute_chop`] by translating each [`Location`] to a corresponding
+/// source [`Span`] for the location.
+pub fn compute_chop_spans<'tcx>(
+  results: &FlowResults<'tcx>,
+  sources: Vec<(Place<'tcx>, LocationOrArg)>,
+  sinks: Vec<(Place<'tcx>, LocationOrArg)>,
+  spanner: &Spanner,
+) -> Vec<Span> {
+  let chop = compute_chop(results, sources, sinks);
+  location_spans(results, &chop, spanner)
 }
@@ -0,0 +1,409 @@
+//! Explanations of why a location is a dependency of a place.
+//!
+//! [`compute_dependencies`](super::compute_dependencies) returns the transitive dependencies of a place
+//! as a flat set. [`explain_dependency`] instead reconstructs a chain of direct steps from one of
+//! those dependencies to the place, by replaying the [`Mutation`]s and control dependencies that
+//! the [`FlowAnalysis`](super::FlowAnalysis) used to compute the set.
+
+use std::{collections::VecDeque, iter, rc::Rc};
+
+use either::Either;
+use log::debug;
+use rustc_data_structures::fx::FxHashSet as HashSet;
+use rustc_middle::mir::{visit::Visitor, *};
+use rustc_utils::{mir::location_or_arg::LocationOrArg, OperandExt};
+use serde::Serialize;
+
+use super::{
+  mutation::{ModularMutationVisitor, Mutation},
+  FlowDomain, FlowResults,
+};
+
+/// How a step of an explanation depends on the previous step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum DependencyReason {
+  /// The previous step is used to compute this step, e.g. `y` in `x = y + 1`.
+  Data,
+
+  /// Whether this step executes depends on the previous step, e.g. `y` in `if y { x = 1 }`.
+  Control,
+
+  /// This step reads or writes through a pointer computed by the previous step, e.g. `p` in `*p = 1`.
+  Provenance,
+
+  /// The previous step is passed to a call whose effects are approximated, e.g. `y` in `x = f(y)`.
+  Call,
+}
+
+/// A place at a location on a chain of dependencies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DependencyStep<'tcx> {
+  /// The place that is written (or for branches, read) at `location`.
+  pub place: Place<'tcx>,
+
+  /// The location of the step.
+  pub location: LocationOrArg,
+
+  /// How this step depends on the previous step, or `None` for the first step.
+  pub reason: Option<DependencyReason>,
+}
+
+/// A program point at which the value of a place is observed.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Point {
+  Before(Location),
+  After(Location),
+}
+
+/// What happens at a step, which determines the steps it depends on.
+#[derive(Clone)]
+enum StepKind<'tcx> {
+  /// The place is read by the target.
+  Target,
+  /// The place is mutated using the given inputs.
+  Mutation(Vec<Place<'tcx>>),
+  /// The place is the discriminant of a branch.
+  Branch,
+  /// The place is an argument.
+  Arg,
+}
+
+struct Node<'tcx> {
+  step: DependencyStep<'tcx>,
+  kind: StepKind<'tcx>,
+  parent: Option<usize>,
+}
+
+struct Explainer<'a, 'tcx> {
+  results: &'a FlowResults<'tcx>,
+  body: &'a Body<'tcx>,
+  dep: LocationOrArg,
+}
+
+impl<'a, 'tcx> Explainer<'a, 'tcx> {
+  fn state(&self, point: Point) -> Rc<FlowDomain<'tcx>> {
+    match point {
+      Point::After(location) => self.results.shared_state_at(location),
+      Point::Before(location) => self.results.state_before(self.body, location),
+    }
+  }
+
+  fn depends_on_dep(&self, place: Place<'tcx>, point: Point) -> bool {
+    let state = self.state(point);
+    self
+      .results
+      .analysis
+      .deps_for(&state, place)
+      .contains(self.dep)
+  }
+
+  fn mutations_at(
+    &self,
+    location: Location,
+  ) -> Vec<(Place<'tcx>, Vec<Place<'tcx>>, bool)> {
+    let analysis = &self.results.analysis;
+    let mut mutations = Vec::new();
+    ModularMutationVisitor::new(&analysis.place_info, |_, mts: Vec<Mutation<'tcx>>| {
+      for mt in mts {
+        let strong = analysis.is_strong_update(&mt);
+        mutations.push((mt.mutated, mt.inputs, strong));
+      }
+    })
+    .visit_location(self.body, location);
+    mutations
+  }
+
+  fn is_call(&self, location: Location) -> bool {
+    matches!(
+      self.body.stmt_at(location),
+      Either::Right(Terminator {
+        kind: TerminatorKind::Call { .. },
+        ..
+      })
+    )
+  }
+
+  /// Returns the steps that last mutated `place` before `point`, along with how `place` depends on them.
+  fn last_mutations(
+    &self,
+    place: Place<'tcx>,
+    point: Point,
+    reason: DependencyReason,
+  ) -> Vec<(Node<'tcx>, DependencyReason)> {
+    let analysis = &self.results.analysis;
+    let place_info = &analysis.place_info;
+    // Inputs are read as in the transfer function, and other places as in `deps_for`
+    let reachable = iter::once(place)
+      .chain(
+        place_info
+          .reachable_values(place, Mutability::Not)
+          .iter()
+          .copied(),
+      )
+      .collect::<Vec<_>>();
+    let value_rows = reachable
+      .iter()
+      .flat_map(|place| analysis.thin_influences(*place))
+      .map(|place| place_info.normalize(place))
+      .collect::<HashSet<_>>();
+    let provenance_rows = reachable
+      .iter()
+      .flat_map(|place| analysis.provenance(*place))
+      .flat_map(|place| place_info.conflicts(place).iter().copied())
+      .map(|place| place_info.normalize(place))
+      .collect::<HashSet<_>>();
+    let reason_for = |rows: &mut dyn Iterator<Item = Place<'tcx>>| {
+      let mut reason_for = None;
+      for row in rows {
+        if value_rows.contains(&row) {
+          return Some(reason);
+        } else if provenance_rows.contains(&row) {
+          reason_for = Some(DependencyReason::Provenance);
+        }
+      }
+      reason_for
+    };
+
+    let predecessors = |location: Location| -> Vec<Location> {
+      if location.statement_index > 0 {
+        vec![Location {
+          block: location.block,
+          statement_index: location.statement_index - 1,
+        }]
+      } else {
+        self.body.basic_blocks.predecessors()[location.block]
+          .iter()
+          .map(|pred| self.body.terminator_loc(*pred))
+          .collect()
+      }
+    };
+
+    let mut found = Vec::new();
+    let mut reached_entry = false;
+    let mut visited = HashSet::default();
+    let mut stack = match point {
+      Point::After(location) => vec![location],
+      Point::Before(location) => {
+        reached_entry |= location == Location::START;
+        predecessors(location)
+      }
+    };
+    while let Some(location) = stack.pop() {
+      if !visited.insert(location) {
+        continue;
+      }
+
+      let mut killed = false;
+      for (mutated, inputs, strong) in self.mutations_at(location) {
+        let mut rows = analysis
+          .mutable_aliases(mutated)
+          .into_iter()
+          .map(|alias| place_info.normalize(alias));
+        let Some(step_reason) = reason_for(&mut rows) else {
+          continue;
+        };
+        killed |= strong && step_reason != DependencyReason::Provenance;
+
+        let step = DependencyStep {
+          place: mutated,
+          location: LocationOrArg::Location(location),
+          reason: None,
+        };
+        let node = Node {
+          step,
+          kind: StepKind::Mutation(inputs),
+          parent: None,
+        };
+        found.push((node, step_reason));
+      }
+
+      if !killed {
+        if location == Location::START {
+          reached_entry = true;
+        }
+        stack.extend(predecessors(location));
+      }
+    }
+
+    if reached_entry {
+      for (arg, index) in place_info.all_args() {
+        let mut rows = place_info
+          .conflicts(arg)
+          .iter()
+          .map(|place| place_info.normalize(*place));
+        if let Some(step_reason) = reason_for(&mut rows) {
+          let step = DependencyStep {
+            place: arg,
+            location: *place_info.location_domain().value(index),
+            reason: None,
+          };
+          let node = Node {
+            step,
+            kind: StepKind::Arg,
+            parent: None,
+          };
+          found.push((node, step_reason));
+        }
+      }
+    }
+
+    found
+  }
+
+  /// Returns the steps that `node` directly depends on, along with how it depends on them.
+  fn predecessors(&self, node: &Node<'tcx>) -> Vec<(Node<'tcx>, DependencyReason)> {
+    let LocationOrArg::Location(location) = node.step.location else {
+      return Vec::new();
+    };
+
+    let mut preds = Vec::new();
+    match &node.kind {
+      StepKind::Target => {
+        preds.extend(self.last_mutations(
+          node.step.place,
+          Point::After(location),
+          DependencyReason::Data,
+        ));
+        return preds;
+      }
+      StepKind::Mutation(inputs) => {
+        let reason = if self.is_call(location) {
+          DependencyReason::Call
+        } else {
+          DependencyReason::Data
+        };
+        for input in inputs {
+          preds.extend(self.last_mutations(*input, Point::Before(location), reason));
+        }
+        for place in self.results.analysis.provenance(node.step.place) {
+          preds.extend(self.last_mutations(
+            place,
+            Point::Before(location),
+            DependencyReason::Provenance,
+          ));
+        }
+      }
+      StepKind::Branch => {
+        preds.extend(self.last_mutations(
+          node.step.place,
+          Point::Before(location),
+          DependencyReason::Data,
+        ));
+      }
+      StepKind::Arg => unreachable!(),
+    }
+
+    let analysis = &self.results.analysis;
+    let controlled_by = analysis.control_dependencies.dependent_on(location.block);
+    for block in controlled_by.into_iter().flat_map(|set| set.iter()) {
+      let terminator = self.body.basic_blocks[block].terminator();
+      let TerminatorKind::SwitchInt { discr, .. } = &terminator.kind else {
+        continue;
+      };
+      let Some(discr_place) = discr.as_place() else {
+        continue;
+      };
+      let step = DependencyStep {
+        place: discr_place,
+        location: LocationOrArg::Location(self.body.terminator_loc(block)),
+        reason: None,
+      };
+      let node = Node {
+        step,
+        kind: StepKind::Branch,
+        parent: None,
+      };
+      preds.push((node, DependencyReason::Control));
+    }
+
+    preds
+  }
+
+  /// Returns true if the dependency could be reached from `node`.
+  fn is_relevant(&self, node: &Node<'tcx>) -> bool {
+    match node.step.location {
+      location if location == self.dep => true,
+      LocationOrArg::Arg(_) => false,
+      LocationOrArg::Location(location) => {
+        self.depends_on_dep(node.step.place, Point::After(location))
+      }
+    }
+  }
+}
+
+/// Explains why `dep_location` is a dependency of the `target` place at a location,
+/// as computed by [`compute_dependencies`](super::compute_dependencies) in the backward direction.
+///
+/// Returns a chain of steps that starts at `dep_location` and ends at the target, where each step
+/// directly depends on the previous one. The chain is a shortest one, and is `None` if
+/// `dep_location` is not a dependency of the target.
+pub fn explain_dependency<'tcx>(
+  results: &FlowResults<'tcx>,
+  target: (Place<'tcx>, LocationOrArg),
+  dep_location: LocationOrArg,
+) -> Option<Vec<DependencyStep<'tcx>>> {
+  let explainer = Explainer {
+    results,
+    body: results.analysis.body,
+    dep: dep_location,
+  };
+
+  let (place, location) = target;
+  let target_step = DependencyStep {
+    place,
+    location,
+    reason: None,
+  };
+  if let LocationOrArg::Arg(_) = location {
+    return (location == dep_location).then(|| vec![target_step]);
+  }
+
+  let mut nodes = vec![Node {
+    step: target_step,
+    kind: StepKind::Target,
+    parent: None,
+  }];
+  let mut visited = HashSet::default();
+  let mut queue = VecDeque::from([0]);
+  let found = 'search: loop {
+    let index = queue.pop_front()?;
+    for (mut pred, reason) in explainer.predecessors(&nodes[index]) {
+      if !visited.insert((pred.step.place, pred.step.location))
+        || !explainer.is_relevant(&pred)
+      {
+        continue;
+      }
+      // Store how the parent depends on this node, and reverse it below
+      pred.step.reason = Some(reason);
+      pred.parent = Some(index);
+      nodes.push(pred);
+      if nodes.last().unwrap().step.location == dep_location {
+        break 'search nodes.len() - 1;
+      }
+      queue.push_back(nodes.len() - 1);
+    }
+  };
+
+  let mut steps = Vec::new();
+  let mut reason = None;
+  let mut current = Some(found);
+  while let Some(index) = current {
+    let node = &nodes[index];
+    steps.push(DependencyStep {
+      reason,
+      ..node.step
+    });
+    reason = node.step.reason;
+    current = node.parent;
+  }
+
+  // The target is its own last mutation if it is written at its location
+  if let [.., last, target] = &steps[..] {
+    if last.location == target.location && last.place == target.place {
+      steps.pop();
+    }
+  }
+
+  debug!("Explanation of {dep_location:?} for {target:?}: {steps:?}");
+  Some(steps)
+}
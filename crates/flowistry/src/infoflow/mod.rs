@@ -1,26 +1,44 @@
 //! The core information flow analysis.
 //!
-//! The main function is [`compute_flow`]. See [`FlowResults`] and [`FlowDomain`] for an explanation
-//! of what it returns.
+//! The main function is [`compute_flow`], or [`compute_flow_with_config`] to configure the analysis.
+//! To analyze every body in a crate, use [`compute_crate_flow`].
+//! To compute the backward dependencies of a few places without analyzing the whole body,
+//! use [`compute_backward_dependencies`].
+//! See [`FlowResults`] and [`FlowDomain`] for an explanation of what it returns.
 
-use std::cell::RefCell;
+use std::{cell::RefCell, rc::Rc};
 
 use log::debug;
 use rustc_borrowck::consumers::BodyWithBorrowckFacts;
-use rustc_hir::BodyId;
+use rustc_data_structures::sync::Lrc;
+use rustc_hir::{def_id::DefId, BodyId};
 use rustc_middle::ty::TyCtxt;
 use rustc_utils::{block_timer, BodyExt};
 
+use self::recursive::{FlowCache, FlowSummary, Instantiation, SummaryTable};
 pub use self::{
   analysis::{FlowAnalysis, FlowDomain},
-  dependencies::{compute_dependencies, compute_dependency_spans, Direction},
+  config::{FlowConfig, FlowDiagnostics, TrackedKinds, UntrackedKind},
+  crate_flow::compute_crate_flow,
+  demand::compute_backward_dependencies,
+  dependencies::{
+    compute_chop, compute_chop_spans, compute_dependencies, compute_dependency_spans,
+    DependencyKind, Direction,
+  },
+  explain::{explain_dependency, DependencyReason, DependencyStep},
 };
-use crate::mir::{engine, placeinfo::PlaceInfo};
+use crate::mir::{engine, placeinfo::PlaceInfo, FlowistryInput};
 
 mod analysis;
+pub mod call_model;
+mod config;
+mod crate_flow;
+mod demand;
 mod dependencies;
+mod explain;
 pub mod mutation;
 mod recursive;
+pub mod summaries;
 
 /// The output of the information flow analysis.
 ///
@@ -35,21 +53,23 @@ mod recursive;
 /// ```
 /// # #![feature(rustc_private)]
 /// # extern crate rustc_middle;
+/// # use std::rc::Rc;
 /// # use rustc_middle::{ty::TyCtxt, mir::{Place, Location, Local}};
 /// # use flowistry::{infoflow::{FlowDomain, FlowResults}};
 /// # use rustc_utils::{mir::location_or_arg::index::LocationOrArgSet, PlaceExt};
 /// fn example<'tcx>(tcx: TyCtxt<'tcx>, results: &FlowResults<'tcx>) {
 ///   let ℓ: Location         = Location::START;
-///   let Θ: &FlowDomain      = results.state_at(ℓ);
+///   let Θ: Rc<FlowDomain>   = results.shared_state_at(ℓ);
 ///   let p: Place            = Place::make(Local::from_usize(1), &[], tcx);
-///   let κ: LocationOrArgSet = results.analysis.deps_for(Θ, p);
+///   let κ: LocationOrArgSet = results.analysis.deps_for(&Θ, p);
 ///   for ℓ2 in κ.iter() {
 ///     println!("at location {ℓ:?}, place {p:?} depends on location {ℓ2:?}");
 ///   }
 /// }
 /// ```
 ///
-/// To access a [`FlowDomain`] for a given location, use the method [`AnalysisResults::state_at`](engine::AnalysisResults::state_at).
+/// To access a [`FlowDomain`] for a given location, use the method [`AnalysisResults::shared_state_at`](engine::AnalysisResults::shared_state_at),
+/// which works for every [`EngineMode`](crate::mir::engine::EngineMode).
 /// See [`FlowDomain`] for more on how to access the location set for a given place.
 ///
 /// **Note:** this analysis uses rustc's [dataflow analysis framework](https://rustc-dev-guide.rust-lang.org/mir/dataflow.html),
@@ -62,7 +82,8 @@ mod recursive;
 pub type FlowResults<'tcx> = engine::AnalysisResults<'tcx, FlowAnalysis<'tcx>>;
 
 thread_local! {
-  pub(super) static BODY_STACK: RefCell<Vec<BodyId>> =
+  /// The bodies currently being analyzed on this thread, from the outermost caller.
+  pub(super) static BODY_STACK: RefCell<Vec<DefId>> =
     RefCell::new(Vec::new());
 }
 
@@ -76,34 +97,113 @@ thread_local! {
 /// function.
 ///
 /// See [`FlowResults`] for an explanation of how to use the return value.
+///
+/// This uses the default [`FlowConfig`], see [`compute_flow_with_config`] to change it.
 pub fn compute_flow<'tcx>(
   tcx: TyCtxt<'tcx>,
   body_id: BodyId,
   body_with_facts: &'tcx BodyWithBorrowckFacts<'tcx>,
 ) -> FlowResults<'tcx> {
-  BODY_STACK.with(|body_stack| {
-    body_stack.borrow_mut().push(body_id);
-    debug!("{}", body_with_facts.body.to_string(tcx).unwrap());
-
-    let def_id = tcx.hir().body_owner_def_id(body_id).to_def_id();
-    let place_info = PlaceInfo::build(tcx, def_id, body_with_facts);
-    let location_domain = place_info.location_domain().clone();
-
-    let body = &body_with_facts.body;
+  compute_flow_with_config(tcx, body_id, body_with_facts, &FlowConfig::default())
+}
 
-    let results = {
-      block_timer!("Flow");
+/// Computes information flow for a MIR body with the given configuration.
+///
+/// Diagnostics about the analysis, such as whether it reached a library function it could
+/// not recurse into, are available from [`FlowAnalysis::diagnostics`] on the `analysis` field
+/// of the results.
+pub fn compute_flow_with_config<'tcx>(
+  tcx: TyCtxt<'tcx>,
+  body_id: BodyId,
+  body_with_facts: &'tcx BodyWithBorrowckFacts<'tcx>,
+  config: &FlowConfig,
+) -> FlowResults<'tcx> {
+  let def_id = tcx.hir().body_owner_def_id(body_id).to_def_id();
+  compute_flow_for_input(
+    tcx,
+    def_id,
+    body_with_facts,
+    Rc::new(config.clone()),
+    Rc::default(),
+    Lrc::default(),
+    None,
+  )
+}
 
-      let analysis = FlowAnalysis::new(tcx, def_id, body, place_info);
-      engine::iterate_to_fixpoint(tcx, body, location_domain, analysis)
-      // analysis.into_engine(tcx, body).iterate_to_fixpoint()
+/// Computes information flow for the body of `def_id` provided by any [`FlowistryInput`].
+///
+/// If the body (transitively) calls itself, then the recursive calls are modeled by a
+/// summary of the body in `summaries`, and the body is re-analyzed until that summary
+/// stops changing. Summaries of callees are shared through `cache` with other analyses,
+/// possibly on other threads. If `instantiation` is given, then calls in the body are
+/// resolved with the body's generic arguments instantiated.
+pub(crate) fn compute_flow_for_input<'tcx>(
+  tcx: TyCtxt<'tcx>,
+  def_id: DefId,
+  input: impl FlowistryInput<'tcx, 'tcx>,
+  config: Rc<FlowConfig>,
+  summaries: Rc<RefCell<SummaryTable<'tcx>>>,
+  cache: Lrc<FlowCache<'tcx>>,
+  instantiation: Option<Instantiation<'tcx>>,
+) -> FlowResults<'tcx> {
+  BODY_STACK.with(|body_stack| {
+    body_stack.borrow_mut().push(def_id);
+    let body = input.body();
+    debug!("{}", body.to_string(tcx).unwrap());
+
+    let results = loop {
+      let place_info = PlaceInfo::build_with_config(tcx, def_id, input, config.clone());
+      let location_domain = place_info.location_domain().clone();
+
+      let results = {
+        block_timer!("Flow");
+
+        let mut analysis = FlowAnalysis::new(tcx, def_id, body, place_info);
+        analysis.summaries = summaries.clone();
+        analysis.cache = cache.clone();
+        analysis.instantiation = instantiation;
+        engine::iterate_to_fixpoint_with_mode(
+          tcx,
+          body,
+          location_domain,
+          analysis,
+          config.engine_mode,
+          &config.budget,
+        )
+        // analysis.into_engine(tcx, body).iterate_to_fixpoint()
+      };
+
+      let mut table = summaries.borrow_mut();
+      if !table.pending.remove(&def_id) {
+        break results;
+      }
+
+      // Every location may depend on a summary that has not reached its fixpoint
+      if results.degraded_locations().is_some() {
+        let mut results = results;
+        results.degrade_all();
+        break results;
+      }
+
+      let summary = FlowSummary::from_results(&results);
+      let changed = table.summaries.entry(def_id).or_default().join(&summary);
+      if !changed {
+        break results;
+      }
+      debug!("Summary of {def_id:?} changed, re-analyzing");
     };
 
+    if results.degraded_locations().is_some() || results.analysis.place_info.is_degraded()
+    {
+      log::info!("Analysis of {def_id:?} exceeded its budget");
+      results.analysis.diagnostics.borrow_mut().degraded = true;
+    }
+
     if log::log_enabled!(log::Level::Info) {
       let counts = body
         .all_locations()
         .flat_map(|loc| {
-          let state = results.state_at(loc);
+          let state = results.shared_state_at(loc);
           state
             .rows()
             .map(|(_, locations)| locations.len())
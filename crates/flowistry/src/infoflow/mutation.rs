@@ -4,7 +4,7 @@ use itertools::Itertools;
 use log::debug;
 use rustc_middle::{
   mir::{visit::Visitor, *},
-  ty::{AdtKind, TyKind},
+  ty::{AdtKind, List, TyKind},
 };
 use rustc_target::abi::FieldIdx;
 use rustc_utils::{mir::place::PlaceCollector, AdtDefExt, OperandExt, PlaceExt};
@@ -70,6 +70,62 @@ where
   pub fn new(place_info: &'a PlaceInfo<'tcx>, f: F) -> Self {
     ModularMutationVisitor { place_info, f }
   }
+
+  /// Returns the places that influence a call through its callee `func`.
+  ///
+  /// A function pointer, `dyn Fn` object, or closure determines which code is executed,
+  /// so it is an input to all of the call's effects. If the callee is a closure (including
+  /// the receiver of a call through one of the `Fn` traits), then the places it captures
+  /// are inputs as well.
+  fn callee_inputs(
+    &self,
+    func: &Operand<'tcx>,
+    args: &[Operand<'tcx>],
+  ) -> Vec<Place<'tcx>> {
+    let tcx = self.place_info.tcx;
+    let body = self.place_info.body;
+
+    let is_fn_trait_call =
+      func
+        .constant()
+        .is_some_and(|func| match func.const_.ty().kind() {
+          TyKind::FnDef(def_id, _) => tcx
+            .trait_of_item(*def_id)
+            .is_some_and(|trait_id| tcx.fn_trait_kind_from_def_id(trait_id).is_some()),
+          _ => false,
+        });
+    let receiver = is_fn_trait_call
+      .then(|| args.first().and_then(|arg| arg.as_place()))
+      .flatten();
+
+    let callees = func.as_place().into_iter().chain(receiver);
+    let values = callees
+      .flat_map(|callee| self.place_info.reachable_values(callee, Mutability::Not))
+      .copied()
+      .collect::<Vec<_>>();
+
+    let upvars = values.iter().flat_map(|value| {
+      let ty = value.ty(body.local_decls(), tcx).ty;
+      let upvar_tys = match ty.kind() {
+        TyKind::Closure(_, closure_args) => closure_args.as_closure().upvar_tys(),
+        _ => List::empty(),
+      };
+      upvar_tys.iter().enumerate().flat_map(move |(i, upvar_ty)| {
+        let upvar = value.project_deeper(
+          &[ProjectionElem::Field(FieldIdx::from_usize(i), upvar_ty)],
+          tcx,
+        );
+        self.place_info.reachable_values(upvar, Mutability::Not)
+      })
+    });
+
+    values
+      .iter()
+      .copied()
+      .chain(upvars.copied())
+      .unique()
+      .collect()
+  }
 }
 
 impl<'tcx, F> Visitor<'tcx> for ModularMutationVisitor<'_, 'tcx, F>
@@ -221,11 +277,22 @@ where
 
     match &terminator.kind {
       TerminatorKind::Call {
-        /*func,*/ // TODO: deal with func
+        func,
         args,
         destination,
         ..
       } => {
+        let config = self.place_info.config();
+        if let Some(mutations) =
+          config
+            .call_models
+            .model_call(&terminator.kind, self.place_info, location)
+        {
+          debug!("  Call is handled by a call model");
+          (self.f)(location, mutations);
+          return;
+        }
+
         let async_hack = AsyncHack::new(
           self.place_info.tcx,
           self.place_info.body,
@@ -233,14 +300,40 @@ where
         );
         let arg_places = utils::arg_places(args)
           .into_iter()
-          .map(|(_, place)| place)
+          .filter(|(_, place)| !async_hack.ignore_place(*place))
+          .collect::<Vec<_>>();
+        let callee_inputs = self
+          .callee_inputs(func, args)
+          .into_iter()
           .filter(|place| !async_hack.ignore_place(*place))
           .collect::<Vec<_>>();
-        let arg_inputs = arg_places
-          .iter()
-          .flat_map(|arg| self.place_info.reachable_values(*arg, Mutability::Not))
-          .copied()
-          .collect_vec();
+
+        // The values reachable from the arguments with the given indices, or all arguments
+        let inputs_from = |indices: Option<&[usize]>| {
+          arg_places
+            .iter()
+            .filter(|(i, _)| indices.map_or(true, |indices| indices.contains(i)))
+            .flat_map(|(_, arg)| self.place_info.reachable_values(*arg, Mutability::Not))
+            .copied()
+            .chain(callee_inputs.iter().copied())
+            .unique()
+            .collect_vec()
+        };
+
+        let summary = match func.constant().map(|func| func.const_.ty().kind()) {
+          Some(TyKind::FnDef(def_id, generic_args)) => config
+            .summaries
+            .find(
+              tcx,
+              tcx.param_env(self.place_info.def_id),
+              *def_id,
+              generic_args,
+            )
+            .cloned(),
+          _ => None,
+        };
+        let summary = summary.unwrap_or_default();
+        debug!("  Using summary {summary:?}");
 
         let ret_is_unit = destination
           .ty(self.place_info.body.local_decls(), tcx)
@@ -249,7 +342,7 @@ where
         let dest_inputs = if ret_is_unit {
           Vec::new()
         } else {
-          arg_inputs.clone()
+          inputs_from(summary.returns.as_deref())
         };
 
         let mut mutations = vec![Mutation {
@@ -259,13 +352,43 @@ where
           status: MutationStatus::Definitely,
         }];
 
-        for (num, arg) in arg_places.into_iter().enumerate() {
+        let mutates = match &summary.mutates {
+          Some(mutates) => mutates
+            .iter()
+            .filter_map(|mutation| {
+              let num = arg_places.iter().position(|(i, _)| *i == mutation.arg)?;
+              Some((num, inputs_from(Some(&mutation.inputs)), mutation.interior))
+            })
+            .collect_vec(),
+          None => {
+            let arg_inputs = inputs_from(None);
+            (0 .. arg_places.len())
+              .map(|num| (num, arg_inputs.clone(), false))
+              .collect_vec()
+          }
+        };
+
+        for (num, inputs, interior) in mutates {
+          let arg = arg_places[num].1;
+          if interior {
+            let arg_ty = arg.ty(self.place_info.body.local_decls(), tcx).ty;
+            if arg_ty.is_ref() {
+              mutations.push(Mutation {
+                mutated: tcx.mk_place_deref(arg),
+                reason: Reason::Argument(num as u8),
+                inputs,
+                status: MutationStatus::Possibly,
+              });
+            }
+            continue;
+          }
+
           for arg_mut in self.place_info.reachable_values(arg, Mutability::Mut) {
             if *arg_mut != arg {
               mutations.push(Mutation {
                 mutated: *arg_mut,
                 reason: Reason::Argument(num as u8),
-                inputs: arg_inputs.clone(),
+                inputs: inputs.clone(),
                 status: MutationStatus::Possibly,
               });
             }
@@ -1,21 +1,252 @@
+use std::sync::Mutex;
+
+use either::Either;
+use indexical::impls::RustcIndexMatrix as IndexMatrix;
 use log::{debug, info};
+use rustc_data_structures::fx::{FxHashMap as HashMap, FxHashSet as HashSet};
+use rustc_hir::{
+  def::DefKind,
+  def_id::{DefId, LocalDefId},
+  intravisit, Block, BlockCheckMode, BodyId, UnsafeSource,
+};
 use rustc_middle::{
   mir::*,
-  ty::{ClosureKind, GenericArgKind, TyKind},
+  ty::{
+    ClosureKind, EarlyBinder, GenericArgKind, GenericArgsRef, Instance, InstanceDef,
+    ParamEnv, TyCtxt, TyKind,
+  },
 };
 use rustc_mir_dataflow::JoinSemiLattice;
-use rustc_utils::{mir::borrowck_facts::get_body_with_borrowck_facts, PlaceExt};
+use rustc_target::abi::FieldIdx;
+use rustc_utils::{
+  mir::borrowck_facts::get_body_with_borrowck_facts, OperandExt, PlaceExt,
+};
 
 use super::{analysis::FlowAnalysis, BODY_STACK};
 use crate::{
-  extensions::REACHED_LIBRARY,
+  extensions::{CrossCrateMode, UnsafeMode},
   infoflow::{
     mutation::{Mutation, MutationStatus, Reason},
-    FlowDomain,
+    FlowDiagnostics, FlowDomain, FlowResults,
   },
-  mir::utils,
+  mir::{cross_crate::CrossCrateBody, utils, FlowistryInput, SharedBody},
 };
 
+/// The effect of a function on its caller, in terms of the function's own places.
+///
+/// Maps each place visible to the caller (the return place, or a place reachable from an
+/// argument) that the function may mutate to the places that the mutation depends on.
+/// A summary is derived from the flow of the function at its return points, and
+/// stands in for that flow at recursive call sites, where it is not yet known.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub(crate) struct FlowSummary<'tcx> {
+  mutations: HashMap<Place<'tcx>, HashSet<Place<'tcx>>>,
+}
+
+impl<'tcx> FlowSummary<'tcx> {
+  /// Summarizes the flow of a function at all of its return points.
+  pub(crate) fn from_results(flow: &FlowResults<'tcx>) -> Self {
+    let body = flow.analysis.body;
+    let mut return_state = IndexMatrix::new(flow.analysis.location_domain());
+    for (bb, data) in body.basic_blocks.iter_enumerated() {
+      if let TerminatorKind::Return = data.terminator().kind {
+        return_state.join(flow.shared_state_at(body.terminator_loc(bb)).deps());
+      }
+    }
+
+    let is_visible =
+      |place: &Place<'tcx>| place.local == RETURN_PLACE || place.is_arg(body);
+
+    let mutations = return_state
+      .rows()
+      .filter(|(child, _)| is_visible(child))
+      .filter_map(|(child, child_deps)| {
+        let was_return = child.local == RETURN_PLACE;
+        // > 1 because arguments will always have their synthetic location in their dep set
+        let was_mutated = child_deps.len() > 1;
+        if !was_mutated && !was_return {
+          return None;
+        }
+
+        let inputs = return_state
+          .rows()
+          .filter(|(row, deps)| is_visible(row) && child_deps.is_superset(deps))
+          .map(|(row, _)| *row)
+          .collect();
+        Some((*child, inputs))
+      })
+      .collect();
+
+    FlowSummary { mutations }
+  }
+
+  /// Adds the mutations of `other` to `self`, returning true if `self` changed.
+  pub(crate) fn join(&mut self, other: &Self) -> bool {
+    let mut changed = false;
+    for (place, inputs) in &other.mutations {
+      let self_inputs = self.mutations.entry(*place).or_insert_with(|| {
+        changed = true;
+        HashSet::default()
+      });
+      for input in inputs {
+        changed |= self_inputs.insert(*input);
+      }
+    }
+    changed
+  }
+}
+
+/// Returns true if the body contains a user-written `unsafe` block.
+fn contains_unsafe_block(tcx: TyCtxt<'_>, body_id: BodyId) -> bool {
+  struct FindUnsafe {
+    found: bool,
+  }
+
+  impl<'hir> intravisit::Visitor<'hir> for FindUnsafe {
+    fn visit_block(&mut self, block: &'hir Block<'hir>) {
+      self.found |= matches!(
+        block.rules,
+        BlockCheckMode::UnsafeBlock(UnsafeSource::UserProvided)
+      );
+      intravisit::walk_block(self, block);
+    }
+  }
+
+  let mut visitor = FindUnsafe { found: false };
+  intravisit::Visitor::visit_body(&mut visitor, tcx.hir().body(body_id));
+  visitor.found
+}
+
+/// The generic arguments that a body is analyzed with, when it is analyzed as the callee
+/// of a call whose generic arguments were resolved.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Instantiation<'tcx> {
+  /// The environment in which `args` are well-formed, i.e. that of the root of the analysis.
+  pub(crate) param_env: ParamEnv<'tcx>,
+
+  /// The generic arguments of the body.
+  pub(crate) args: GenericArgsRef<'tcx>,
+}
+
+/// The summaries of recursive functions, shared by all analyses started from the same
+/// call to [`compute_flow`](super::compute_flow).
+#[derive(Default)]
+pub(crate) struct SummaryTable<'tcx> {
+  pub(crate) summaries: HashMap<DefId, FlowSummary<'tcx>>,
+
+  /// Functions whose summary has been used at a recursive call site,
+  /// and which therefore need to be re-analyzed until their summary stabilizes.
+  pub(crate) pending: HashSet<DefId>,
+
+  /// The position in [`BODY_STACK`] of the outermost function whose summary has been
+  /// used at a recursive call site. The results of any function above that position
+  /// depend on a summary that may still change.
+  pub(crate) outermost_use: Option<usize>,
+}
+
+/// A callee with its generic arguments, in the environment of the caller.
+pub(crate) type CallKey<'tcx> = (DefId, GenericArgsRef<'tcx>, ParamEnv<'tcx>);
+
+/// The summary of a callee, with the diagnostics of its analysis.
+pub(crate) type CalleeSummary<'tcx> = (FlowSummary<'tcx>, FlowDiagnostics);
+
+/// The bodies of a typeck root and of the closures within it, which are borrow-checked
+/// together.
+#[derive(Default)]
+struct BodyGroup<'tcx> {
+  owners: Vec<LocalDefId>,
+
+  /// The bodies once they have been borrow-checked. The lock is held while borrow-checking,
+  /// so each group is borrow-checked by exactly one thread.
+  bodies: Mutex<Option<HashMap<LocalDefId, SharedBody<'tcx>>>>,
+}
+
+impl<'tcx> BodyGroup<'tcx> {
+  /// Returns the borrow-checked body of `def_id`, borrow-checking the group on the current
+  /// thread if no thread has done so yet.
+  fn body(&self, tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> SharedBody<'tcx> {
+    let mut bodies = self.bodies.lock().unwrap();
+    let bodies = bodies.get_or_insert_with(|| {
+      // Borrow-checking steals the MIR that the unsafety check of a callee reads
+      for def_id in &self.owners {
+        if matches!(tcx.def_kind(*def_id), DefKind::Fn | DefKind::AssocFn) {
+          tcx.ensure().mir_unsafety_check_result(*def_id);
+        }
+      }
+
+      // `rustc_utils` stores borrow-checked bodies in a thread-local cache, so the whole
+      // group is retrieved on this thread, which also borrow-checks the closures
+      self
+        .owners
+        .iter()
+        .map(|def_id| {
+          let body_with_facts = get_body_with_borrowck_facts(tcx, *def_id);
+          (*def_id, SharedBody::new(body_with_facts))
+        })
+        .collect()
+    });
+    bodies[&def_id]
+  }
+}
+
+/// The state shared by all analyses started from the same call to
+/// [`compute_flow`](super::compute_flow) or [`compute_crate_flow`](super::compute_crate_flow),
+/// which may run on several threads.
+#[derive(Default)]
+pub(crate) struct FlowCache<'tcx> {
+  /// Summaries of callees whose analysis did not depend on an unfinished summary,
+  /// with the diagnostics of that analysis.
+  summaries: Mutex<HashMap<CallKey<'tcx>, CalleeSummary<'tcx>>>,
+
+  /// The bodies of the crate grouped by typeck root, if they are shared between threads.
+  groups: HashMap<LocalDefId, BodyGroup<'tcx>>,
+}
+
+impl<'tcx> FlowCache<'tcx> {
+  /// Creates a cache that shares the bodies of the local crate between threads.
+  pub(crate) fn for_crate(tcx: TyCtxt<'tcx>) -> Self {
+    let mut groups = HashMap::<_, BodyGroup>::default();
+    for def_id in tcx.hir().body_owners() {
+      let root = tcx.typeck_root_def_id(def_id.to_def_id()).expect_local();
+      groups.entry(root).or_default().owners.push(def_id);
+    }
+    FlowCache {
+      summaries: Mutex::default(),
+      groups,
+    }
+  }
+
+  /// Borrow-checks the groups whose bodies may be borrow-checked as a side effect of
+  /// a query about another body, on the current thread.
+  ///
+  /// These are the constants, whose evaluation requires their borrow-checked MIR,
+  /// and the functions that define an opaque type, whose hidden type is revealed by
+  /// borrow-checking them. Once they are borrow-checked, every other group is only
+  /// borrow-checked when its bodies are requested.
+  pub(crate) fn borrowck_side_effects(&self, tcx: TyCtxt<'tcx>) {
+    for (root, group) in &self.groups {
+      if tcx.hir().body_const_context(*root).is_some()
+        || !tcx.opaque_types_defined_by(*root).is_empty()
+      {
+        group.body(tcx, *root);
+      }
+    }
+  }
+
+  /// Returns the borrow-checked body of `def_id`.
+  ///
+  /// If bodies are shared between threads, then the body is borrow-checked by the first
+  /// thread to request a body of its group. Otherwise it is borrow-checked on the
+  /// current thread.
+  pub(crate) fn body(&self, tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> SharedBody<'tcx> {
+    let root = tcx.typeck_root_def_id(def_id.to_def_id()).expect_local();
+    match self.groups.get(&root) {
+      Some(group) => group.body(tcx, def_id),
+      None => SharedBody::new(get_body_with_borrowck_facts(tcx, def_id)),
+    }
+  }
+}
+
 impl<'tcx> FlowAnalysis<'tcx> {
   pub(crate) fn recurse_into_call(
     &self,
@@ -35,6 +266,17 @@ impl<'tcx> FlowAnalysis<'tcx> {
     };
     debug!("Checking whether can recurse into {func:?}");
 
+    // Call models take precedence over the callee's body, see ModularMutationVisitor
+    let config = self.config();
+    if config
+      .call_models
+      .model_call(call, &self.place_info, location)
+      .is_some()
+    {
+      debug!("  Func has a call model");
+      return false;
+    }
+
     let func = match func.constant() {
       Some(func) => func,
       None => {
@@ -43,52 +285,361 @@ impl<'tcx> FlowAnalysis<'tcx> {
       }
     };
 
-    let def_id = match func.const_.ty().kind() {
-      TyKind::FnDef(def_id, _) => def_id,
+    let (def_id, generic_args) = match func.const_.ty().kind() {
+      TyKind::FnDef(def_id, generic_args) => (*def_id, generic_args),
       _ => {
         debug!("  Func is not a FnDef");
         return false;
       }
     };
 
-    // If a function returns never (fn () -> !) then there are no exit points,
-    // so we can't analyze effects on exit
-    let fn_sig = tcx.fn_sig(*def_id);
-    if fn_sig.skip_binder().output().skip_binder().is_never() {
-      debug!("  Func returns never");
+    // Resolve trait methods and generic functions to the body that is actually called.
+    // If this body is itself an instantiation of a generic function, then the generic
+    // arguments of the call are first instantiated with those of this body.
+    let (param_env, generic_args) = match self.instantiation {
+      Some(instantiation) => (
+        instantiation.param_env,
+        EarlyBinder::bind(tcx.erase_regions(*generic_args))
+          .instantiate(tcx, instantiation.args),
+      ),
+      None => (tcx.param_env(self.def_id), *generic_args),
+    };
+
+    // Summaries take precedence over the callee's body, see ModularMutationVisitor
+    if config
+      .summaries
+      .find(tcx, param_env, def_id, generic_args)
+      .is_some()
+    {
+      debug!("  Func has a summary");
+      return false;
+    }
+
+    let (def_id, instantiation) =
+      match Instance::resolve(tcx, param_env, def_id, generic_args) {
+        Ok(Some(Instance {
+          def: InstanceDef::Item(resolved_id),
+          args,
+        })) => (resolved_id, Some(Instantiation { param_env, args })),
+        // If the call cannot be resolved (e.g. `T::method()` for a type parameter `T`),
+        // then the body of a trait method is only a default, which may be overridden.
+        _ if tcx.trait_of_item(def_id).is_some() => {
+          debug!("  Func is a trait method that could not be resolved");
+          return false;
+        }
+        _ => (def_id, None),
+      };
+    debug!("  Func resolves to {def_id:?} with {instantiation:?}");
+
+    // Closure bodies take their arguments untupled, unlike calls through the Fn traits
+    let is_closure = tcx.is_closure_or_coroutine(def_id);
+    if is_closure && tcx.coroutine_kind(def_id).is_some() {
+      debug!("  Func is a coroutine");
       return false;
     }
 
-    let node = match tcx.hir().get_if_local(*def_id) {
-      Some(node) => node,
+    let reached_library = || {
+      self.diagnostics.borrow_mut().reached_library = true;
+    };
+
+    let eval_mode = &config.eval_mode;
+    let recurse_unsafe = eval_mode.unsafe_mode == UnsafeMode::Recurse;
+    let (input, has_unsafe) = match tcx.hir().get_if_local(def_id) {
+      Some(node) => {
+        let body_id = match node.body_id() {
+          Some(body_id) => body_id,
+          None => {
+            debug!("  Func does not have a BodyId");
+            return false;
+          }
+        };
+
+        // Closures are checked for unsafety along with their enclosing function,
+        // whose MIR may already have been consumed, so check their HIR instead
+        let has_unsafe = if is_closure {
+          contains_unsafe_block(tcx, body_id)
+        } else {
+          let unsafety = tcx.mir_unsafety_check_result(def_id.expect_local());
+          !unsafety.used_unsafe_blocks.is_empty()
+        };
+        if has_unsafe && !recurse_unsafe {
+          debug!("  Func contains unsafe blocks");
+          return false;
+        }
+
+        (Either::Left(body_id), has_unsafe)
+      }
+      None if eval_mode.cross_crate_mode == CrossCrateMode::Recurse => {
+        let Some(input) = CrossCrateBody::load(tcx, def_id) else {
+          debug!("  Func does not have MIR available");
+          reached_library();
+          return false;
+        };
+
+        let has_unsafe = input.derefs_raw_pointer(tcx);
+        if has_unsafe && !recurse_unsafe {
+          debug!("  Func dereferences a raw pointer");
+          reached_library();
+          return false;
+        }
+
+        (Either::Right(input), has_unsafe)
+      }
       None => {
         debug!("  Func is not in local crate");
-        REACHED_LIBRARY.get(|reached_library| {
-          if let Some(reached_library) = reached_library {
-            *reached_library.borrow_mut() = true;
-          }
-        });
+        reached_library();
         return false;
       }
     };
 
-    let body_id = match node.body_id() {
-      Some(body_id) => body_id,
-      None => {
-        debug!("  Func does not have a BodyId");
-        return false;
+    let parent_arg_places = if is_closure {
+      self.closure_arg_places(parent_args)
+    } else {
+      utils::arg_places(parent_args)
+    };
+
+    // The position of the callee on the stack if it is recursive, or the position it
+    // will be analyzed at otherwise
+    let (recursive, depth) = BODY_STACK.with(|body_stack| {
+      let body_stack = body_stack.borrow();
+      match body_stack
+        .iter()
+        .position(|visited_id| *visited_id == def_id)
+      {
+        Some(depth) => (true, depth),
+        None => (false, body_stack.len()),
       }
+    });
+
+    let body = match input {
+      Either::Left(_) => self.cache.body(tcx, def_id.expect_local()).body(),
+      Either::Right(input) => input.body(),
     };
 
-    let unsafety = tcx.mir_unsafety_check_result(def_id.expect_local());
-    if !unsafety.used_unsafe_blocks.is_empty() {
-      debug!("  Func contains unsafe blocks");
+    // If a function returns never (fn () -> !) then there are no exit points,
+    // so we can't analyze effects on exit
+    if body.return_ty().is_never() {
+      debug!("  Func returns never");
       return false;
     }
 
-    let parent_arg_places = utils::arg_places(parent_args);
-    let any_closure_inputs = parent_arg_places.iter().any(|(_, place)| {
-      let ty = place.ty(self.body.local_decls(), tcx).ty;
+    let summary = if recursive {
+      // The callee is still being analyzed further up the stack, so use its summary
+      // so far. compute_flow_for_input will re-analyze the callee until its summary
+      // reaches a fixpoint.
+      debug!("  Func is a recursive call, using its current summary");
+      if has_unsafe {
+        self.diagnostics.borrow_mut().add_unsafe_callee(def_id);
+      }
+      let mut summaries = self.summaries.borrow_mut();
+      summaries.pending.insert(def_id);
+      summaries.outermost_use = Some(match summaries.outermost_use {
+        Some(outermost) => outermost.min(depth),
+        None => depth,
+      });
+      summaries
+        .summaries
+        .get(&def_id)
+        .cloned()
+        .unwrap_or_default()
+    } else {
+      let key = (def_id, generic_args, param_env);
+      let cached = self.recurse_cache.borrow().get(&key).cloned();
+      let cached =
+        cached.or_else(|| self.cache.summaries.lock().unwrap().get(&key).cloned());
+      let (summary, callee_diagnostics) = cached.unwrap_or_else(|| {
+        info!("Recursing into {}", tcx.def_path_debug_str(def_id));
+        let config = self.place_info.config.clone();
+        let summaries = self.summaries.clone();
+        let cache = self.cache.clone();
+        let outer_use = summaries.borrow_mut().outermost_use.take();
+        let flow = match input {
+          Either::Left(_) => {
+            let body = cache.body(tcx, def_id.expect_local());
+            super::compute_flow_for_input(
+              tcx,
+              def_id,
+              body,
+              config,
+              summaries,
+              cache,
+              instantiation,
+            )
+          }
+          Either::Right(input) => super::compute_flow_for_input(
+            tcx,
+            def_id,
+            input,
+            config,
+            summaries,
+            cache,
+            instantiation,
+          ),
+        };
+        let entry = (
+          FlowSummary::from_results(&flow),
+          flow.analysis.diagnostics.into_inner(),
+        );
+
+        // Uses of summaries of the callee or its own callees were resolved by the
+        // callee's fixpoint, but uses of summaries further down the stack were not.
+        // Then the callee's summary is only final for this analysis.
+        let mut table = self.summaries.borrow_mut();
+        let inner_use = table.outermost_use.filter(|outermost| *outermost < depth);
+        table.outermost_use = outer_use.into_iter().chain(inner_use).min();
+        if inner_use.is_none() {
+          self
+            .cache
+            .summaries
+            .lock()
+            .unwrap()
+            .insert(key, entry.clone());
+        }
+        self.recurse_cache.borrow_mut().insert(key, entry.clone());
+        entry
+      });
+
+      let mut diagnostics = self.diagnostics.borrow_mut();
+      diagnostics.join(&callee_diagnostics);
+
+      // The summary of a callee that exceeded its budget may be missing some flows
+      if callee_diagnostics.degraded {
+        debug!("  Func exceeded its budget");
+        return false;
+      }
+
+      if has_unsafe {
+        diagnostics.add_unsafe_callee(def_id);
+      }
+
+      summary
+    };
+
+    let translate_child_to_parent = |child: Place<'tcx>, mutated: bool| {
+      self.translate_child_to_parent(
+        body,
+        &parent_arg_places,
+        *destination,
+        child,
+        mutated,
+      )
+    };
+
+    let mutations = summary
+      .mutations
+      .iter()
+      .flat_map(|(child, child_inputs)| {
+        let parent_deps = child_inputs
+          .iter()
+          .filter_map(|input| translate_child_to_parent(*input, false))
+          .collect::<Vec<_>>();
+
+        // Mutations to a closure passed by value are not visible to the parent,
+        // except for the places that the closure captures by mutable reference.
+        let (parent, visible) = match translate_child_to_parent(*child, true) {
+          Some(parent) => (Some(parent), true),
+          None => (translate_child_to_parent(*child, false), false),
+        };
+        let parents = parent
+          .into_iter()
+          .flat_map(|parent| {
+            let captures = self.mutable_captures(parent);
+            visible.then_some(parent).into_iter().chain(captures)
+          })
+          .collect::<Vec<_>>();
+
+        debug!("child {child:?} / inputs {child_inputs:?}\n-->\nparents {parents:?} / parent_deps {parent_deps:?}");
+
+        // The argument of the call that the child was passed as, which is the closure
+        // itself for the places it captures. The untupled arguments of a closure are all
+        // passed in the tuple after the closure.
+        let was_return = child.local == RETURN_PLACE;
+        let arg = if is_closure {
+          child.local.as_usize().saturating_sub(1).min(1)
+        } else {
+          child.local.as_usize().saturating_sub(1)
+        };
+        parents.into_iter().map(move |parent| Mutation {
+          mutated: parent,
+          inputs: parent_deps.clone(),
+          reason: if was_return {
+            Reason::AssignTarget
+          } else {
+            Reason::Argument(arg as u8)
+          },
+          status: if was_return {
+            MutationStatus::Definitely
+          } else {
+            MutationStatus::Possibly
+          },
+        })
+      })
+      .collect::<Vec<_>>();
+
+    self.transfer_function(state, mutations, location);
+
+    true
+  }
+
+  /// Returns the places passed as arguments to a closure called through one of the
+  /// `Fn` traits, i.e. `call(env, (arg1, arg2, ..))`.
+  ///
+  /// The closure's body takes its arguments untupled, so the fields of the tuple are
+  /// returned as separate arguments following the closure environment.
+  fn closure_arg_places(&self, args: &[Operand<'tcx>]) -> Vec<(usize, Place<'tcx>)> {
+    let mut arg_places = utils::arg_places(&args[.. 1]);
+    if let Some(tuple) = args.get(1).and_then(|arg| arg.as_place()) {
+      let tuple_ty = tuple.ty(self.body.local_decls(), self.tcx).ty;
+      let fields = tuple_ty.tuple_fields().iter().enumerate().map(|(i, ty)| {
+        let field = tuple.project_deeper(
+          &[ProjectionElem::Field(FieldIdx::from_usize(i), ty)],
+          self.tcx,
+        );
+        (i + 1, field)
+      });
+      arg_places.extend(fields);
+    }
+    arg_places
+  }
+
+  /// If `place` is a closure, returns the places that it captures by mutable reference.
+  ///
+  /// Calling the closure may mutate these places, even if the closure itself is
+  /// passed by value.
+  fn mutable_captures(&self, place: Place<'tcx>) -> Vec<Place<'tcx>> {
+    let ty = place.ty(self.body.local_decls(), self.tcx).ty;
+    let TyKind::Closure(_, closure_args) = ty.kind() else {
+      return Vec::new();
+    };
+    closure_args
+      .as_closure()
+      .upvar_tys()
+      .iter()
+      .enumerate()
+      .filter(|(_, upvar_ty)| upvar_ty.ref_mutability() == Some(Mutability::Mut))
+      .map(|(i, upvar_ty)| {
+        place.project_deeper(
+          &[
+            ProjectionElem::Field(FieldIdx::from_usize(i), upvar_ty),
+            ProjectionElem::Deref,
+          ],
+          self.tcx,
+        )
+      })
+      .collect()
+  }
+
+  /// Returns true if any of the places passed as arguments to a call contains an
+  /// `FnMut` or `FnOnce` closure.
+  ///
+  /// Mutations performed by calling such a closure are not visible through the
+  /// callee's arguments, so the callee cannot be precisely summarized at the call site.
+  pub(crate) fn has_mutable_closure_inputs(
+    &self,
+    parent_arg_places: &[(usize, Place<'tcx>)],
+  ) -> bool {
+    parent_arg_places.iter().any(|(_, place)| {
+      let ty = place.ty(self.body.local_decls(), self.tcx).ty;
       ty.walk().any(|arg| match arg.unpack() {
         GenericArgKind::Type(ty) => match ty.kind() {
           TyKind::Closure(_, substs) => matches!(
@@ -99,145 +650,87 @@ impl<'tcx> FlowAnalysis<'tcx> {
         },
         _ => false,
       })
-    });
-    if any_closure_inputs {
-      debug!("  Func has closure inputs");
-      return false;
+    })
+  }
+
+  /// Translates a place `child` in the body of a callee into the corresponding place
+  /// in the caller `self.body`, given the places passed as arguments to the call and its
+  /// `destination`.
+  ///
+  /// Only the return place and places reachable from arguments can be translated. If
+  /// `mutated` is true, then direct argument places are not translated, since mutations
+  /// to them are not visible to the caller.
+  pub(crate) fn translate_child_to_parent(
+    &self,
+    child_body: &Body<'tcx>,
+    parent_arg_places: &[(usize, Place<'tcx>)],
+    destination: Place<'tcx>,
+    child: Place<'tcx>,
+    mutated: bool,
+  ) -> Option<Place<'tcx>> {
+    let tcx = self.tcx;
+    let child_ty = child.ty(child_body.local_decls(), tcx).ty;
+    if child_ty.is_unit() {
+      return None;
     }
 
-    let recursive = BODY_STACK.with(|body_stack| {
-      let body_stack = body_stack.borrow();
-      body_stack.iter().any(|visited_id| *visited_id == body_id)
-    });
-    if recursive {
-      debug!("  Func is a recursive call");
-      return false;
+    let can_translate = child.local == RETURN_PLACE
+      || (child.is_arg(child_body) && (!mutated || child.is_indirect()));
+    if !can_translate {
+      return None;
     }
 
-    let body_with_facts = get_body_with_borrowck_facts(tcx, def_id.expect_local());
-    let mut recurse_cache = self.recurse_cache.borrow_mut();
-    let flow = recurse_cache.entry(body_id).or_insert_with(|| {
-      info!("Recursing into {}", tcx.def_path_debug_str(*def_id));
-      super::compute_flow(tcx, body_id, body_with_facts)
-    });
-    let body = &body_with_facts.body;
+    // For example, say we're calling f(_5.0) and child = (*_1).1 where
+    // .1 is private to parent. Then:
+    //    parent_toplevel_arg = _5.0
+    //    child.projection = (*□).1
+    //    parent_arg_projected = (*_5.0)
 
-    let mut return_state = FlowDomain::new(flow.analysis.location_domain());
-    {
-      let return_locs = body
-        .basic_blocks
-        .iter_enumerated()
-        .filter_map(|(bb, data)| match data.terminator().kind {
-          TerminatorKind::Return => Some(body.terminator_loc(bb)),
-          _ => None,
-        });
-
-      for loc in return_locs {
-        return_state.join(flow.state_at(loc));
-      }
+    let parent_toplevel_arg = if child.local == RETURN_PLACE {
+      destination
+    } else {
+      parent_arg_places
+        .iter()
+        .find(|(j, _)| child.local.as_usize() - 1 == *j)
+        .map(|(_, place)| *place)?
     };
 
-    let translate_child_to_parent = |child: Place<'tcx>,
-                                     mutated: bool|
-     -> Option<Place<'tcx>> {
-      let child_ty = child.ty(body.local_decls(), tcx).ty;
-      if child_ty.is_unit() {
-        return None;
+    let mut projection = parent_toplevel_arg.projection.to_vec();
+    let mut ty = parent_toplevel_arg.ty(self.body.local_decls(), tcx);
+    let parent_param_env = tcx.param_env(self.def_id);
+    log::debug!("Adding child {child:?} to parent {parent_toplevel_arg:?}");
+    for elem in child.projection.iter() {
+      // The child may be an instantiation of a generic function in the parent,
+      // so its places can have more structure than the parent's type parameters
+      if matches!(ty.ty.kind(), TyKind::Param(_) | TyKind::Alias(..)) {
+        break;
       }
 
-      let can_translate = child.local == RETURN_PLACE
-        || (child.is_arg(body) && (!mutated || child.is_indirect()));
-      if !can_translate {
-        return None;
-      }
-
-      // For example, say we're calling f(_5.0) and child = (*_1).1 where
-      // .1 is private to parent. Then:
-      //    parent_toplevel_arg = _5.0
-      //    child.projection = (*□).1
-      //    parent_arg_projected = (*_5.0)
-
-      let parent_toplevel_arg = if child.local == RETURN_PLACE {
-        *destination
-      } else {
-        parent_arg_places
-          .iter()
-          .find(|(j, _)| child.local.as_usize() - 1 == *j)
-          .map(|(_, place)| *place)?
-      };
-
-      let mut projection = parent_toplevel_arg.projection.to_vec();
-      let mut ty = parent_toplevel_arg.ty(self.body.local_decls(), tcx);
-      let parent_param_env = tcx.param_env(self.def_id);
-      log::debug!("Adding child {child:?} to parent {parent_toplevel_arg:?}");
-      for elem in child.projection.iter() {
-        // Don't continue if we reach a private field
-        if let ProjectionElem::Field(field, _) = elem {
-          if let Some(adt_def) = ty.ty.ty_adt_def() {
-            let field = adt_def.all_fields().nth(field.as_usize()).unwrap();
-            if !field.vis.is_accessible_from(self.def_id, self.tcx) {
-              break;
-            }
+      // Don't continue if we reach a private field
+      if let ProjectionElem::Field(field, _) = elem {
+        if let Some(adt_def) = ty.ty.ty_adt_def() {
+          let field = adt_def.all_fields().nth(field.as_usize()).unwrap();
+          if !field.vis.is_accessible_from(self.def_id, self.tcx) {
+            break;
           }
         }
-
-        ty = ty.projection_ty_core(
-          tcx,
-          parent_param_env,
-          &elem,
-          |_, field, _| ty.field_ty(tcx, field),
-          |_, ty| ty,
-        );
-        let elem = match elem {
-          ProjectionElem::Field(field, _) => ProjectionElem::Field(field, ty.ty),
-          elem => elem,
-        };
-        projection.push(elem);
       }
 
-      let parent_arg_projected = Place::make(parent_toplevel_arg.local, &projection, tcx);
-      Some(parent_arg_projected)
-    };
-
-    let mutations = return_state.rows().filter_map(|(child, _)| {
-      let parent = translate_child_to_parent(*child, true)?;
-
-      let was_return: bool = child.local == RETURN_PLACE;
-      // > 1 because arguments will always have their synthetic location in their dep set
-      let was_mutated = return_state.row_set(child).len() > 1;
-      if !was_mutated && !was_return {
-        return None;
-      }
-
-      let child_deps = return_state.row_set(child);
-      let parent_deps = return_state
-        .rows()
-        .filter(|(_, deps)| child_deps.is_superset(deps))
-        .filter_map(|(row, _)| translate_child_to_parent(*row, false))
-        .collect::<Vec<_>>();
-
-      debug!(
-        "child {child:?} \n  / child_deps {child_deps:?}\n-->\nparent {parent:?}\n   / parent_deps {parent_deps:?}"
+      ty = ty.projection_ty_core(
+        tcx,
+        parent_param_env,
+        &elem,
+        |_, field, _| ty.field_ty(tcx, field),
+        |_, ty| ty,
       );
+      let elem = match elem {
+        ProjectionElem::Field(field, _) => ProjectionElem::Field(field, ty.ty),
+        elem => elem,
+      };
+      projection.push(elem);
+    }
 
-      Some(Mutation {
-        mutated: parent,
-        inputs: parent_deps.clone(),
-        reason: if was_return {
-          Reason::AssignTarget
-        } else {
-          Reason::Argument(parent.local.as_u32() as u8 - 1)
-        },
-        status: if was_return {
-          MutationStatus::Definitely
-        } else {
-          MutationStatus::Possibly
-        },
-      })
-    }).collect::<Vec<_>>();
-
-    self.transfer_function(state, mutations, location);
-
-    true
+    let parent_arg_projected = Place::make(parent_toplevel_arg.local, &projection, tcx);
+    Some(parent_arg_projected)
   }
 }
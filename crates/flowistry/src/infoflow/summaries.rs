@@ -0,0 +1,129 @@
+//! User-supplied summaries of the information flow through functions.
+//!
+//! By default, a call that is not recursed into is handled by the
+//! [modular approximation](super::mutation::ModularMutationVisitor), which assumes that
+//! every argument flows to the return value, and that everything reachable from a
+//! mutable reference passed to the function is mutated. A [`CallSummaries`] table replaces
+//! these assumptions for specific functions. Summaries are written in TOML, keyed by the
+//! def path of the function as printed by [`TyCtxt::def_path_str`]:
+//!
+//! ```toml
+//! ["std::vec::Vec::<T, A>::push"]
+//! return = []
+//! mutates = [{ arg = 0, inputs = [1] }]
+//! ```
+//!
+//! Arguments are referred to by their zero-based index. `return` lists the arguments
+//! that flow to the return value, and `mutates` lists the arguments whose mutable pointees
+//! may be mutated by the call along with the arguments that flow into them. Either key
+//! can be omitted to use the modular approximation for that part of the call. A mutation
+//! with `interior = true` instead mutates the pointee of a shared reference, for functions
+//! like `Cell::set` that mutate through interior mutability.
+//!
+//! Calls to trait methods are looked up by the path of the resolved implementation
+//! (e.g. `<std::vec::Vec<T, A> as std::ops::DerefMut>::deref_mut`) if one can be found,
+//! and then by the path of the trait method.
+//!
+//! Summaries are only used if they are included in the [`FlowConfig`](super::FlowConfig)
+//! of the analysis. [`CallSummaries::std`] contains summaries for common APIs of the
+//! standard library.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rustc_data_structures::fx::FxHashMap as HashMap;
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::{GenericArgsRef, Instance, ParamEnv, TyCtxt};
+use serde::{Deserialize, Serialize};
+
+/// The effect of a call on a single argument.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArgMutation {
+  /// The index of the argument whose mutable pointees are mutated.
+  pub arg: usize,
+
+  /// The indices of the arguments that flow into the mutated places.
+  pub inputs: Vec<usize>,
+
+  /// If true, the argument is a shared reference whose pointee is mutated through
+  /// interior mutability, e.g. the receiver of `Cell::set`.
+  #[serde(default)]
+  pub interior: bool,
+}
+
+/// The information flow through a call to a function.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CallSummary {
+  /// The indices of the arguments that flow to the return value, or `None` for all of them.
+  #[serde(rename = "return")]
+  pub returns: Option<Vec<usize>>,
+
+  /// The arguments mutated by the call, or `None` for every mutable argument.
+  pub mutates: Option<Vec<ArgMutation>>,
+}
+
+/// A table of [`CallSummary`]s, keyed by def path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct CallSummaries(HashMap<String, CallSummary>);
+
+const STD_SUMMARIES: &str = include_str!("std_summaries.toml");
+
+impl CallSummaries {
+  /// Parses a table of summaries from a TOML string.
+  pub fn from_toml(contents: &str) -> Result<Self> {
+    Ok(toml::from_str(contents)?)
+  }
+
+  /// Reads a table of summaries from a TOML file.
+  pub fn load(path: &Path) -> Result<Self> {
+    let contents = std::fs::read_to_string(path)
+      .with_context(|| format!("Failed to read summaries from {}", path.display()))?;
+    Self::from_toml(&contents)
+      .with_context(|| format!("Failed to parse summaries in {}", path.display()))
+  }
+
+  /// Returns the bundled summaries for common APIs of the standard library.
+  pub fn std() -> Self {
+    Self::from_toml(STD_SUMMARIES).expect("Bundled summaries are invalid")
+  }
+
+  /// Adds the summaries in `other`, overriding any existing summaries for the same path.
+  pub fn extend(&mut self, other: CallSummaries) {
+    self.0.extend(other.0);
+  }
+
+  /// Inserts a summary for the function with the given def path.
+  pub fn insert(&mut self, path: impl Into<String>, summary: CallSummary) {
+    self.0.insert(path.into(), summary);
+  }
+
+  /// Returns the summary for the function with the given def path.
+  pub fn get(&self, path: &str) -> Option<&CallSummary> {
+    self.0.get(path)
+  }
+
+  /// Returns the summary for a call to `def_id` with `args` in the environment `param_env`.
+  pub fn find<'tcx>(
+    &self,
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    def_id: DefId,
+    args: GenericArgsRef<'tcx>,
+  ) -> Option<&CallSummary> {
+    if self.0.is_empty() {
+      return None;
+    }
+
+    let resolved = match Instance::resolve(tcx, param_env, def_id, args) {
+      Ok(Some(instance)) if instance.def_id() != def_id => Some(instance.def_id()),
+      _ => None,
+    };
+    resolved
+      .into_iter()
+      .chain([def_id])
+      .find_map(|def_id| self.get(&tcx.def_path_str(def_id)))
+  }
+}
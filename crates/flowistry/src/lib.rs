@@ -67,6 +67,7 @@ extern crate rustc_macros;
 extern crate rustc_middle;
 extern crate rustc_mir_dataflow;
 extern crate rustc_mir_transform;
+extern crate rustc_query_system;
 extern crate rustc_serialize;
 extern crate rustc_session;
 extern crate rustc_span;
@@ -78,5 +79,7 @@ extern crate smallvec;
 pub mod extensions;
 pub mod infoflow;
 pub mod mir;
+#[cfg(feature = "pdg")]
+pub mod pdg;
 #[cfg(feature = "test")]
 pub mod test_utils;
@@ -15,14 +15,20 @@ use rustc_index::{
 };
 use rustc_middle::{
   mir::{visit::Visitor, *},
-  ty::{Region, RegionKind, RegionVid, Ty, TyCtxt, TyKind, TypeAndMut},
+  ty::{
+    GenericArgKind, ParamEnv, Region, RegionKind, RegionVid, Ty, TyCtxt, TyKind,
+    TypeAndMut,
+  },
 };
 use rustc_utils::{mir::place::UNKNOWN_REGION, timer::elapsed, PlaceExt};
 
 use super::FlowistryInput;
 use crate::{
-  extensions::{is_extension_active, PointerMode},
-  mir::utils::{AsyncHack, PlaceSet},
+  extensions::{EvalMode, PointerMode, UnsafeMode},
+  mir::{
+    budget::Budget,
+    utils::{AsyncHack, PlaceSet},
+  },
 };
 
 #[derive(Default)]
@@ -52,11 +58,147 @@ impl<'tcx> Visitor<'tcx> for GatherBorrows<'tcx> {
 type LoanSet<'tcx> = HashSet<(Place<'tcx>, Mutability)>;
 type LoanMap<'tcx> = HashMap<RegionVid, LoanSet<'tcx>>;
 
+/// Returns true if values of `ty` may share ownership of interior-mutable data with
+/// values stored elsewhere, i.e. if `ty` mentions an `Rc`, an `Arc`, or a guard
+/// returned by `RefCell::borrow(_mut)`, `Mutex::lock` or `RwLock::{read, write}`.
+///
+/// Such values are not related to each other by lifetimes, so they are handled
+/// separately by [`Aliases::shared_owners`].
+fn is_shared_owner<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> bool {
+  ty.walk().any(|arg| match arg.unpack() {
+    GenericArgKind::Type(ty) => match ty.kind() {
+      TyKind::Adt(adt_def, _) => {
+        tcx
+          .get_diagnostic_name(adt_def.did())
+          .map_or(false, |name| {
+            matches!(
+              name.as_str(),
+              "Rc"
+                | "Arc"
+                | "RefCellRef"
+                | "RefCellRefMut"
+                | "MutexGuard"
+                | "RwLockReadGuard"
+                | "RwLockWriteGuard"
+            )
+          })
+      }
+      _ => false,
+    },
+    _ => false,
+  })
+}
+
+/// Computes the classes of locals that may share ownership of the same interior-mutable data.
+///
+/// A local that holds a shared owner is put into the same class as:
+/// * the locals it was moved or copied from, e.g. `y = Result::unwrap(move x)`.
+/// * the locals it was produced from by a call, e.g. `y = <Arc<T> as Clone>::clone(&x)`
+///   or `y = Mutex::lock(&x)`, where a reference argument counts if its pointee is a
+///   shared owner or has interior mutability.
+struct GatherSharedOwners<'a, 'tcx> {
+  tcx: TyCtxt<'tcx>,
+  body: &'tcx Body<'tcx>,
+  param_env: ParamEnv<'tcx>,
+  aliases: &'a Aliases<'tcx>,
+  parent: IndexVec<Local, Local>,
+}
+
+impl<'tcx> GatherSharedOwners<'_, 'tcx> {
+  fn find(&mut self, local: Local) -> Local {
+    let parent = self.parent[local];
+    if parent == local {
+      return local;
+    }
+    let root = self.find(parent);
+    self.parent[local] = root;
+    root
+  }
+
+  fn union(&mut self, a: Local, b: Local) {
+    let (a, b) = (self.find(a), self.find(b));
+    if a != b {
+      self.parent[a] = b;
+    }
+  }
+
+  fn is_shared_owner(&self, place: Place<'tcx>) -> bool {
+    is_shared_owner(self.tcx, place.ty(self.body.local_decls(), self.tcx).ty)
+  }
+
+  fn add_flow(&mut self, dst: Place<'tcx>, src: Place<'tcx>, through_refs: bool) {
+    if self.is_shared_owner(src) {
+      self.union(dst.local, src.local);
+    }
+
+    let src_ty = src.ty(self.body.local_decls(), self.tcx).ty;
+    let TyKind::Ref(_, pointee_ty, _) = src_ty.kind() else {
+      return;
+    };
+    if through_refs
+      && (is_shared_owner(self.tcx, *pointee_ty)
+        || !pointee_ty.is_freeze(self.tcx, self.param_env))
+    {
+      let pointee = self.tcx.mk_place_deref(src);
+      for alias in self.aliases.loan_aliases(pointee) {
+        self.union(dst.local, alias.local);
+      }
+    }
+  }
+}
+
+impl<'tcx> Visitor<'tcx> for GatherSharedOwners<'_, 'tcx> {
+  fn visit_assign(
+    &mut self,
+    place: &Place<'tcx>,
+    rvalue: &Rvalue<'tcx>,
+    _location: Location,
+  ) {
+    if !self.is_shared_owner(*place) {
+      return;
+    }
+
+    let operands = match rvalue {
+      Rvalue::Use(op) | Rvalue::Cast(_, op, _) => std::slice::from_ref(op),
+      Rvalue::Aggregate(_, ops) => &ops.raw,
+      _ => return,
+    };
+    for op in operands {
+      if let Some(src) = op.place() {
+        self.add_flow(*place, src, false);
+      }
+    }
+  }
+
+  fn visit_terminator(&mut self, terminator: &Terminator<'tcx>, _location: Location) {
+    if let TerminatorKind::Call {
+      args, destination, ..
+    } = &terminator.kind
+    {
+      if !self.is_shared_owner(*destination) {
+        return;
+      }
+
+      for arg in args {
+        if let Some(src) = arg.place() {
+          self.add_flow(*destination, src, true);
+        }
+      }
+    }
+  }
+}
+
 /// Data structure for computing and storing aliases.
 pub struct Aliases<'tcx> {
   tcx: TyCtxt<'tcx>,
   body: &'tcx Body<'tcx>,
   pub(super) loans: LoanMap<'tcx>,
+
+  /// For each local that shares ownership of interior-mutable data with other locals,
+  /// all the locals in its class (see [`GatherSharedOwners`]).
+  shared_owners: HashMap<Local, Vec<Local>>,
+
+  degraded: bool,
 }
 
 rustc_index::newtype_index! {
@@ -66,25 +208,80 @@ rustc_index::newtype_index! {
 }
 
 impl<'tcx> Aliases<'tcx> {
-  /// Runs the alias analysis on a given `body_with_facts`.
+  /// Runs the alias analysis on a given `body_with_facts`, using the pointer and
+  /// unsafe modes of `eval_mode`, and stopping early if it exceeds the `budget`.
   pub fn build<'a>(
     tcx: TyCtxt<'tcx>,
     def_id: DefId,
     input: impl FlowistryInput<'tcx, 'a>,
+    eval_mode: &EvalMode,
+    budget: &Budget,
   ) -> Self {
-    let loans = Self::compute_loans(tcx, def_id, input);
-    Aliases {
+    let (loans, degraded) = Self::compute_loans(tcx, def_id, input, eval_mode, budget);
+    let mut aliases = Aliases {
       tcx,
       body: input.body(),
       loans,
+      shared_owners: HashMap::default(),
+      degraded,
+    };
+    aliases.shared_owners = aliases.compute_shared_owners(def_id);
+    aliases
+  }
+
+  fn compute_shared_owners(&self, def_id: DefId) -> HashMap<Local, Vec<Local>> {
+    let mut gather = GatherSharedOwners {
+      tcx: self.tcx,
+      body: self.body,
+      param_env: self.tcx.param_env(def_id),
+      aliases: self,
+      parent: self.body.local_decls().indices().collect(),
+    };
+    gather.visit_body(self.body);
+
+    let mut classes: HashMap<Local, Vec<Local>> = HashMap::default();
+    for local in self.body.local_decls().indices() {
+      let root = gather.find(local);
+      classes.entry(root).or_default().push(local);
     }
+
+    let mut shared_owners = HashMap::default();
+    for class in classes.into_values().filter(|class| class.len() > 1) {
+      debug!("Shared owners: {class:?}");
+      for local in &class {
+        shared_owners.insert(*local, class.clone());
+      }
+    }
+    shared_owners
+  }
+
+  /// Returns true if the fixpoint exceeded its budget, in which case every region
+  /// is assumed to contain every loan.
+  pub fn is_degraded(&self) -> bool {
+    self.degraded
   }
 
+  /// Returns the locals that may share ownership of interior-mutable data with `local`,
+  /// e.g. clones of the same `Rc`, or a `MutexGuard` and the `Mutex` it was locked from.
+  /// The result includes `local` itself, unless it shares ownership with no other local.
+  pub fn shared_owners(&self, local: Local) -> impl Iterator<Item = Local> + '_ {
+    self
+      .shared_owners
+      .get(&local)
+      .into_iter()
+      .flatten()
+      .copied()
+  }
+
+  /// Computes the loans contained in each region, and whether the fixpoint exceeded
+  /// the `budget`.
   fn compute_loans<'a>(
     tcx: TyCtxt<'tcx>,
     def_id: DefId,
     input: impl FlowistryInput<'tcx, 'a>,
-  ) -> LoanMap<'tcx> {
+    eval_mode: &EvalMode,
+    budget: &Budget,
+  ) -> (LoanMap<'tcx>, bool) {
     let start = Instant::now();
     let body = input.body();
     let static_region = RegionVid::from_usize(0);
@@ -116,7 +313,7 @@ impl<'tcx> Aliases<'tcx> {
     let ignore_regions = async_hack.ignore_regions();
 
     // subset('a, 'b) :- subset_base('a, 'b, _).
-    for (a, b) in input.input_facts_subset_base()  {
+    for (a, b) in input.input_facts_subset_base() {
       if ignore_regions.contains(&a) || ignore_regions.contains(&b) {
         continue;
       }
@@ -128,7 +325,7 @@ impl<'tcx> Aliases<'tcx> {
       subset.insert(static_region, a);
     }
 
-    if is_extension_active(|mode| mode.pointer_mode == PointerMode::Conservative) {
+    if eval_mode.pointer_mode == PointerMode::Conservative {
       // for all p1 : &'a T, p2: &'b T: subset('a, 'b).
       let mut region_to_pointers: HashMap<_, Vec<_>> = HashMap::default();
       for (region, places) in &all_pointers {
@@ -258,12 +455,25 @@ impl<'tcx> Aliases<'tcx> {
     //
     // Rather than iterating over the entire subset relation, we only do local fixpoints
     // within each strongly-connected component.
+    //
+    // If the fixpoint exceeds its budget, then every region contains every loan found so far.
+    // Any loan found later would be a projection of one of those loans, which conflicts with it.
+    // See FlowAnalysis::is_strong_update for how the coarser loans are handled.
     let start = Instant::now();
-    for r in all_regions {
+    for r in all_regions.clone() {
       contains.entry(r).or_default();
     }
-    for scc_idx in scc_order {
+    let mut iterations = 0;
+    let mut domain_size = 0;
+    let mut degraded = false;
+    'fixpoint: for scc_idx in scc_order {
       loop {
+        if budget.is_exceeded(iterations, domain_size) {
+          degraded = true;
+          break 'fixpoint;
+        }
+        iterations += 1;
+
         let mut changed = false;
         let scc = &scc_to_regions[scc_idx];
         for a in scc.iter() {
@@ -300,6 +510,7 @@ impl<'tcx> Aliases<'tcx> {
                 changed |= b_contains.len() != orig_len;
               }
             }
+            domain_size = domain_size.max(b_contains.len());
           }
         }
 
@@ -310,11 +521,43 @@ impl<'tcx> Aliases<'tcx> {
     }
     elapsed("fixpoint", start);
 
+    if degraded {
+      info!("Alias analysis exceeded its budget after {iterations} iterations");
+      let all_loans = contains
+        .iter()
+        .filter(|(region, _)| **region != UNKNOWN_REGION)
+        .flat_map(|(_, loans)| loans.iter().copied())
+        .collect::<LoanSet>();
+      for r in all_regions {
+        contains.insert(r, all_loans.clone());
+      }
+    }
+
+    // If unsafe code is analyzed, then raw pointers may have been cast from any reference.
+    // For all places p : *T, loans l: contains('UNK, l).
+    let has_raw_pointers = all_pointers.iter().any(|(region, places)| {
+      *region == UNKNOWN_REGION
+        && places
+          .iter()
+          .any(|(place, _)| place.ty(body.local_decls(), tcx).ty.is_unsafe_ptr())
+    });
+    if has_raw_pointers && eval_mode.unsafe_mode == UnsafeMode::Recurse {
+      let all_loans = contains
+        .iter()
+        .filter(|(region, _)| **region != UNKNOWN_REGION)
+        .flat_map(|(_, loans)| loans.iter().copied())
+        .collect::<Vec<_>>();
+      contains
+        .entry(UNKNOWN_REGION)
+        .or_default()
+        .extend(all_loans);
+    }
+
     info!(
       "Final places in loan set: {}",
       contains.values().map(|set| set.len()).sum::<usize>()
     );
-    contains
+    (contains, degraded)
   }
 
   /// Given a `place`, returns the set of direct places it could refer to.
@@ -333,7 +576,32 @@ impl<'tcx> Aliases<'tcx> {
   /// ```
   ///
   /// The place `*n` is an alias for `v` (even though they have different types!).
+  ///
+  /// Any place that [shares ownership](Self::shared_owners) of interior-mutable data
+  /// with an alias is also an alias. For example, in the program:
+  /// ```
+  /// # use std::sync::{Arc, Mutex};
+  /// let x = Arc::new(Mutex::new(0));
+  /// let y = x.clone();
+  /// ```
+  ///
+  /// The places `x` and `y` are aliases of each other.
   pub fn aliases(&self, place: Place<'tcx>) -> PlaceSet<'tcx> {
+    let aliases = self.loan_aliases(place);
+    if self.shared_owners.is_empty() {
+      return aliases;
+    }
+
+    let shared = aliases
+      .iter()
+      .flat_map(|alias| self.shared_owners(alias.local))
+      .map(|local| Place::from_local(local, self.tcx))
+      .collect::<Vec<_>>();
+    aliases.into_iter().chain(shared).collect()
+  }
+
+  /// Computes the aliases of `place` that are determined by loans alone.
+  fn loan_aliases(&self, place: Place<'tcx>) -> PlaceSet<'tcx> {
     let mut aliases = HashSet::default();
 
     // Argument places can be indirect
@@ -439,7 +707,13 @@ mod test {
     test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
       let body = &body_with_facts.body;
       let def_id = tcx.hir().body_owner_def_id(body_id);
-      let aliases = Aliases::build(tcx, def_id.to_def_id(), body_with_facts);
+      let aliases = Aliases::build(
+        tcx,
+        def_id.to_def_id(),
+        body_with_facts,
+        &EvalMode::default(),
+        &Budget::default(),
+      );
 
       f(tcx, body, aliases)
     });
@@ -471,6 +745,42 @@ mod test {
     });
   }
 
+  #[test]
+  fn test_aliases_budget() {
+    let input = r#"
+    fn main() {
+      let a = 1;
+      let b = 2;
+      let c = &a;
+      let d = &b;
+    }
+    "#;
+    test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+      let body = &body_with_facts.body;
+      let def_id = tcx.hir().body_owner_def_id(body_id);
+      let budget = Budget {
+        max_iterations: Some(0),
+        ..Default::default()
+      };
+      let aliases = Aliases::build(
+        tcx,
+        def_id.to_def_id(),
+        body_with_facts,
+        &EvalMode::default(),
+        &budget,
+      );
+      assert!(aliases.is_degraded());
+
+      // Without a fixpoint, `*d` may alias any loan
+      let p = Placer::new(tcx, body);
+      let d_deref = p.local("d").deref().mk();
+      compare_sets(
+        aliases.aliases(d_deref),
+        hashset! { p.local("a").mk(), p.local("b").mk() },
+      );
+    });
+  }
+
   #[test]
   fn test_aliases_projection() {
     let input = r#"
@@ -497,4 +807,31 @@ fn main() {
       );
     });
   }
+
+  #[test]
+  fn test_aliases_shared_owners() {
+    let input = r#"
+use std::sync::{Arc, Mutex};
+fn main() {
+  let a = Arc::new(Mutex::new(0));
+  let b = a.clone();
+  let c = Arc::new(Mutex::new(0));
+}
+    "#;
+    alias_harness(input, |tcx, body, aliases| {
+      let p = Placer::new(tcx, body);
+      let a = p.local("a").mk();
+      let b = p.local("b").mk();
+
+      // `b` is a clone of `a`, so they share ownership of the same `Mutex`
+      assert!(aliases.aliases(a).contains(&b));
+      assert!(aliases.aliases(b).contains(&a));
+
+      // `c` is a separate allocation
+      compare_sets(
+        aliases.aliases(p.local("c").mk()),
+        hashset! { p.local("c").mk() },
+      );
+    });
+  }
 }
@@ -0,0 +1,72 @@
+//! Limits on the resources used by the fixpoints of the analyses.
+
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+
+/// Limits on the time, iterations and memory used by a fixpoint, and a flag to cancel it.
+///
+/// A fixpoint that exceeds its budget stops early, and returns a sound over-approximation
+/// of its result. The alias analysis assumes that every reference may point to every loan,
+/// and the information flow analysis assumes that everything depends on everything
+/// after the locations it did not finish, see
+/// [`AnalysisResults::degraded_locations`](super::engine::AnalysisResults::degraded_locations).
+/// The results then report that they are degraded in
+/// [`FlowDiagnostics::degraded`](crate::infoflow::FlowDiagnostics::degraded).
+///
+/// The default budget is unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+  /// The time after which every fixpoint stops.
+  pub deadline: Option<Instant>,
+
+  /// The maximum number of iterations of each fixpoint.
+  pub max_iterations: Option<usize>,
+
+  /// The maximum size of the domain at one point of a fixpoint, i.e. the number of loans
+  /// of a region for the alias analysis, or the number of rows of a
+  /// [`FlowDomain`](crate::infoflow::FlowDomain) for the information flow analysis.
+  pub max_domain_size: Option<usize>,
+
+  /// A flag that cancels every fixpoint when it is set, e.g. from another thread.
+  pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Budget {
+  /// Creates a budget whose deadline is `time` from now.
+  pub fn with_time_limit(time: Duration) -> Self {
+    Budget {
+      deadline: Some(Instant::now() + time),
+      ..Default::default()
+    }
+  }
+
+  /// Returns true if the budget is cancelled.
+  pub fn is_cancelled(&self) -> bool {
+    self
+      .cancel
+      .as_ref()
+      .map_or(false, |cancel| cancel.load(Ordering::Relaxed))
+  }
+
+  /// Returns true if a fixpoint that ran for `iterations` iterations, and reached a domain
+  /// of size `domain_size`, must stop.
+  pub fn is_exceeded(&self, iterations: usize, domain_size: usize) -> bool {
+    self.max_iterations.map_or(false, |max| iterations >= max)
+      || self.max_domain_size.map_or(false, |max| domain_size > max)
+      || self
+        .deadline
+        .map_or(false, |deadline| Instant::now() >= deadline)
+      || self.is_cancelled()
+  }
+}
+
+/// A domain whose size can be limited by a [`Budget`].
+pub trait DomainSize {
+  /// Returns the size of the domain, see [`Budget::max_domain_size`].
+  fn domain_size(&self) -> usize;
+}
@@ -0,0 +1,113 @@
+//! Analysis inputs for functions defined in other crates.
+//!
+//! Borrowck facts are only computed for bodies in the local crate. For other crates,
+//! the only MIR available is the MIR encoded into crate metadata, i.e. [`optimized_mir`]
+//! or [`mir_for_ctfe`]. That MIR has all regions erased, so [`CrossCrateBody`] gives every
+//! erased region a fresh variable and conservatively assumes that all of them are equal.
+//!
+//! [`optimized_mir`]: rustc_middle::ty::TyCtxt::optimized_mir
+//! [`mir_for_ctfe`]: rustc_middle::ty::TyCtxt::mir_for_ctfe
+
+use polonius_engine::FactTypes;
+use rustc_borrowck::consumers::RustcFacts;
+use rustc_hir::def_id::DefId;
+use rustc_middle::{
+  mir::{visit::Visitor, Body, Location, Place, ProjectionElem},
+  ty::{Region, RegionKind, RegionVid, TyCtxt},
+};
+
+use super::FlowistryInput;
+
+/// The MIR body of a function in another crate, with conservative borrowck facts.
+#[derive(Clone, Copy)]
+pub struct CrossCrateBody<'tcx> {
+  body: &'tcx Body<'tcx>,
+  num_regions: usize,
+}
+
+impl<'tcx> CrossCrateBody<'tcx> {
+  /// Loads the MIR for `def_id` from crate metadata, returning `None` if no MIR was encoded.
+  pub fn load(tcx: TyCtxt<'tcx>, def_id: DefId) -> Option<Self> {
+    let body = if tcx.is_mir_available(def_id) {
+      tcx.optimized_mir(def_id)
+    } else if tcx.is_const_fn_raw(def_id) {
+      tcx.mir_for_ctfe(def_id)
+    } else {
+      return None;
+    };
+
+    // Region 0 is reserved for 'static
+    let mut num_regions = 1;
+    let body = tcx.fold_regions(body.clone(), |region, _| match region.kind() {
+      RegionKind::ReErased => {
+        let region = Region::new_var(tcx, RegionVid::from_usize(num_regions));
+        num_regions += 1;
+        region
+      }
+      _ => region,
+    });
+
+    Some(CrossCrateBody {
+      body: tcx.arena.alloc(body),
+      num_regions,
+    })
+  }
+
+  /// Returns true if the body dereferences a raw pointer.
+  ///
+  /// Unlike local bodies, the results of unsafety checking are not available for
+  /// other crates, so this is used as an approximation.
+  pub fn derefs_raw_pointer(self, tcx: TyCtxt<'tcx>) -> bool {
+    struct FindRawDeref<'a, 'tcx> {
+      tcx: TyCtxt<'tcx>,
+      body: &'a Body<'tcx>,
+      found: bool,
+    }
+
+    impl<'tcx> Visitor<'tcx> for FindRawDeref<'_, 'tcx> {
+      fn visit_place(
+        &mut self,
+        place: &Place<'tcx>,
+        _context: rustc_middle::mir::visit::PlaceContext,
+        _location: Location,
+      ) {
+        self.found |= place.iter_projections().any(|(place_ref, elem)| {
+          elem == ProjectionElem::Deref
+            && place_ref.ty(self.body, self.tcx).ty.is_unsafe_ptr()
+        });
+      }
+    }
+
+    let mut visitor = FindRawDeref {
+      tcx,
+      body: self.body,
+      found: false,
+    };
+    visitor.visit_body(self.body);
+    visitor.found
+  }
+}
+
+impl<'tcx> FlowistryInput<'tcx, 'tcx> for CrossCrateBody<'tcx> {
+  fn body(self) -> &'tcx Body<'tcx> {
+    self.body
+  }
+
+  /// Relates every region in a cycle, so they all end up in a single equivalence class.
+  fn input_facts_subset_base(
+    self,
+  ) -> Box<
+    dyn Iterator<
+        Item = (
+          <RustcFacts as FactTypes>::Origin,
+          <RustcFacts as FactTypes>::Origin,
+        ),
+      > + 'tcx,
+  > {
+    let n = self.num_regions;
+    Box::new((1 .. n).map(move |i| {
+      let next = if i + 1 == n { 1 } else { i + 1 };
+      (RegionVid::from_usize(i), RegionVid::from_usize(next))
+    }))
+  }
+}
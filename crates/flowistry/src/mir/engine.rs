@@ -10,25 +10,120 @@
 //! allocating / cloning / dropping the domain, not doing computation. Therefore this
 //! engine improves performance but increases memory usage by up-front materializing
 //! the domain at every [`Location`].
+//!
+//! For bodies where that memory is not available, [`iterate_to_fixpoint_with_mode`] takes an
+//! [`EngineMode`] that selects a strategy which only stores the domain once per basic block,
+//! either recomputing the domain within a block like rustc does, or storing the rows
+//! that change at each location of the block.
+//!
+//! The fixpoint stops early if it exceeds its [`Budget`], in which case the domain is only
+//! an under-approximation at the locations it did not finish, see
+//! [`AnalysisResults::degraded_locations`].
 
-use std::rc::Rc;
+use std::{cell::RefCell, rc::Rc, str::FromStr};
 
 use either::Either;
 use indexical::ToIndex;
 use rustc_data_structures::{graph::WithSuccessors, work_queue::WorkQueue};
 use rustc_index::IndexVec;
 use rustc_middle::{
-  mir::{traversal, Body, Location},
+  mir::{traversal, BasicBlock, Body, Location, Statement, START_BLOCK},
   ty::TyCtxt,
 };
-use rustc_mir_dataflow::{Analysis, Direction, JoinSemiLattice, ResultsVisitor};
+use rustc_mir_dataflow::{
+  Analysis, AnalysisDomain, Direction, JoinSemiLattice, ResultsVisitor,
+};
 use rustc_utils::{
   mir::location_or_arg::{
-    index::{LocationOrArgDomain, LocationOrArgIndex},
+    index::{LocationOrArgDomain, LocationOrArgIndex, LocationOrArgSet},
     LocationOrArg,
   },
   BodyExt,
 };
+use serde::{Deserialize, Serialize};
+
+use super::budget::{Budget, DomainSize};
+
+/// How [`iterate_to_fixpoint_with_mode`] stores the results of an analysis.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, Hash)]
+pub enum EngineMode {
+  /// Store the domain at every location. This is the fastest mode, but uses the most memory.
+  #[default]
+  AllLocations,
+  /// Store the domain once per basic block, and recompute the domain at the statements of a
+  /// block when one of them is requested. This uses the least memory, but repeats
+  /// the work of the analysis for every block that is requested.
+  Blocks,
+  /// Store the domain once per basic block, along with the rows that each statement of the
+  /// block changes, i.e. a delta encoding of the domains within the block. The domain at a
+  /// statement is rebuilt on request by applying the deltas to the domain at the start
+  /// of its block, so only the changed rows are stored but no row is shared between domains.
+  RowDeltas,
+}
+
+impl FromStr for EngineMode {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "AllLocations" => Ok(Self::AllLocations),
+      "Blocks" => Ok(Self::Blocks),
+      "RowDeltas" => Ok(Self::RowDeltas),
+      _ => Err(format!("Could not parse: {s}")),
+    }
+  }
+}
+
+/// An [`Analysis`] whose statement effects can be recomputed after it reaches a fixpoint,
+/// as needed by [`EngineMode::Blocks`].
+///
+/// Statement effects must only depend on the state they are applied to. Terminator effects
+/// (e.g. of calls) are never recomputed, so they may depend on the context of the analysis.
+pub trait StatementEffect<'tcx>: Analysis<'tcx> {
+  /// Applies the effect of `statement` to `state`, like
+  /// [`Analysis::apply_statement_effect`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_mir_dataflow/trait.Analysis.html#tymethod.apply_statement_effect).
+  fn statement_effect(
+    &self,
+    state: &mut Self::Domain,
+    statement: &Statement<'tcx>,
+    location: Location,
+  );
+}
+
+/// A domain that can be stored as the rows that differ from another value of the domain,
+/// as needed by [`EngineMode::RowDeltas`].
+pub trait DiffDomain {
+  /// The rows that differ between two values of the domain.
+  type Diff;
+
+  /// Returns the rows of `self` that differ from those of `prev`, including rows of
+  /// `prev` that `self` does not have.
+  fn diff(&self, prev: &Self) -> Self::Diff;
+
+  /// Replaces the rows of `self` with those in `diff`.
+  fn apply_diff(&mut self, diff: &Self::Diff);
+}
+
+/// Recomputes the state after the statement at a location from the state before it.
+type Replay<'tcx, A> =
+  Box<dyn Fn(&A, &mut <A as AnalysisDomain<'tcx>>::Domain, Location) + 'tcx>;
+
+/// The states at the statements of a block.
+type BlockStates<D> = (BasicBlock, Vec<Rc<D>>);
+
+/// The stored states of an analysis, see [`EngineMode`].
+enum States<'tcx, A: Analysis<'tcx>> {
+  AllLocations(IndexVec<LocationOrArgIndex, Rc<A::Domain>>),
+  Blocks {
+    body: &'tcx Body<'tcx>,
+    /// The state at the terminator of each block.
+    exits: IndexVec<BasicBlock, Rc<A::Domain>>,
+    /// Recomputes the effect of a statement, either by reapplying the analysis
+    /// or by applying the stored deltas.
+    replay: Replay<'tcx, A>,
+    /// The states at the statements of the last block that was requested.
+    last_block: RefCell<Option<BlockStates<A::Domain>>>,
+  },
+}
 
 /// An alternative implementation of
 /// [`rustc_mir_dataflow::Results`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_mir_dataflow/struct.Results.html).
@@ -36,7 +131,8 @@ pub struct AnalysisResults<'tcx, A: Analysis<'tcx>> {
   /// The underlying analysis that was used to generate the results.
   pub analysis: A,
   location_domain: Rc<LocationOrArgDomain>,
-  state: IndexVec<LocationOrArgIndex, Rc<A::Domain>>,
+  states: States<'tcx, A>,
+  degraded_locations: Option<LocationOrArgSet>,
 }
 
 impl<'tcx, A: Analysis<'tcx>> AnalysisResults<'tcx, A> {
@@ -51,8 +147,7 @@ impl<'tcx, A: Analysis<'tcx>> AnalysisResults<'tcx, A> {
           block,
           statement_index,
         };
-        let loc_index = location.to_index(&self.location_domain);
-        let state = Rc::clone(&self.state[loc_index]);
+        let state = self.shared_state_at(location);
 
         if statement_index == 0 {
           visitor.visit_block_start(&state);
@@ -79,11 +174,135 @@ impl<'tcx, A: Analysis<'tcx>> AnalysisResults<'tcx, A> {
 
   /// Gets the computed [`AnalysisDomain`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_mir_dataflow/trait.AnalysisDomain.html)
   /// at a given [`Location`].
+  ///
+  /// # Panics
+  /// If the results were not computed with [`EngineMode::AllLocations`], in which case
+  /// use [`AnalysisResults::shared_state_at`].
+  #[deprecated(
+    note = "panics unless the engine mode is `AllLocations`, use `shared_state_at`"
+  )]
   pub fn state_at(&self, location: Location) -> &A::Domain {
-    &self.state[location.to_index(&self.location_domain)]
+    match &self.states {
+      States::AllLocations(states) => &states[location.to_index(&self.location_domain)],
+      States::Blocks { .. } => {
+        panic!("state_at requires EngineMode::AllLocations, use shared_state_at")
+      }
+    }
+  }
+
+  /// Gets the computed domain at a given [`Location`] for any [`EngineMode`].
+  ///
+  /// Unless the results were computed with [`EngineMode::AllLocations`], the domain at a
+  /// statement is rebuilt on request, along with the domains at the other statements of its
+  /// block. The domains of the last requested block are kept, so it is faster to request
+  /// locations block by block.
+  pub fn shared_state_at(&self, location: Location) -> Rc<A::Domain> {
+    let (body, exits, replay, last_block) = match &self.states {
+      States::AllLocations(states) => {
+        return Rc::clone(&states[location.to_index(&self.location_domain)]);
+      }
+      States::Blocks {
+        body,
+        exits,
+        replay,
+        last_block,
+      } => (body, exits, replay, last_block),
+    };
+
+    let data = &body.basic_blocks[location.block];
+    if location.statement_index == data.statements.len() {
+      return Rc::clone(&exits[location.block]);
+    }
+
+    let mut last_block = last_block.borrow_mut();
+    if let Some((block, states)) = &*last_block {
+      if *block == location.block {
+        return Rc::clone(&states[location.statement_index]);
+      }
+    }
+
+    let mut state = block_entry(&self.analysis, body, location.block, exits);
+    let states = (0 .. data.statements.len())
+      .map(|statement_index| {
+        replay(&self.analysis, &mut state, Location {
+          block: location.block,
+          statement_index,
+        });
+        Rc::new(state.clone())
+      })
+      .collect::<Vec<_>>();
+    let result = Rc::clone(&states[location.statement_index]);
+    *last_block = Some((location.block, states));
+    result
+  }
+
+  /// Gets the computed domain immediately before the instruction at a given [`Location`] of `body`.
+  ///
+  /// This is the domain after the previous statement of the block, or at the start of
+  /// a block the join of the domains after the terminators of its predecessors.
+  pub fn state_before(&self, body: &Body<'tcx>, location: Location) -> Rc<A::Domain> {
+    if location.statement_index > 0 {
+      return self.shared_state_at(Location {
+        block: location.block,
+        statement_index: location.statement_index - 1,
+      });
+    }
+
+    Rc::new(join_predecessors(
+      &self.analysis,
+      body,
+      location.block,
+      |pred| self.shared_state_at(body.terminator_loc(pred)),
+    ))
+  }
+
+  /// Returns the locations where the fixpoint was not reached because the analysis
+  /// exceeded its [`Budget`], or `None` if the analysis reached a fixpoint.
+  ///
+  /// These are the locations that are reachable from a location the analysis did not
+  /// finish. The domain at such a location is an under-approximation, so
+  /// a client must assume that everything there may depend on everything.
+  /// The domain at any other location is the same as if the analysis had reached a fixpoint.
+  pub fn degraded_locations(&self) -> Option<&LocationOrArgSet> {
+    self.degraded_locations.as_ref()
+  }
+
+  /// Marks every location as degraded.
+  pub(crate) fn degrade_all(&mut self) {
+    let mut locations = LocationOrArgSet::new(&self.location_domain);
+    locations.insert_all();
+    self.degraded_locations = Some(locations);
   }
 }
 
+/// Returns the state at the start of `block`, given the states at the terminators of all blocks.
+fn block_entry<'tcx, A: Analysis<'tcx>>(
+  analysis: &A,
+  body: &Body<'tcx>,
+  block: BasicBlock,
+  exits: &IndexVec<BasicBlock, Rc<A::Domain>>,
+) -> A::Domain {
+  join_predecessors(analysis, body, block, |pred| Rc::clone(&exits[pred]))
+}
+
+/// Returns the join of the states at the terminators of the predecessors of `block`,
+/// including the initial state if `block` is the start block.
+fn join_predecessors<'tcx, A: Analysis<'tcx>>(
+  analysis: &A,
+  body: &Body<'tcx>,
+  block: BasicBlock,
+  exit: impl Fn(BasicBlock) -> Rc<A::Domain>,
+) -> A::Domain {
+  let mut state = analysis.bottom_value(body);
+  if block == START_BLOCK {
+    analysis.initialize_start_block(body, &mut state);
+  }
+  for pred in &body.basic_blocks.predecessors()[block] {
+    state.join(&exit(*pred));
+  }
+  state
+}
+
 /// Runs a given [`Analysis`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_mir_dataflow/trait.Analysis.html) to a fixpoint over the given [`Body`].
 ///
 /// A reimplementation of [`rustc_mir_dataflow::framework::engine::iterate_to_fixpoint`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_mir_dataflow/framework/engine/struct.Engine.html#method.iterate_to_fixpoint).
@@ -93,6 +312,152 @@ pub fn iterate_to_fixpoint<'tcx, A: Analysis<'tcx>>(
   location_domain: Rc<LocationOrArgDomain>,
   mut analysis: A,
 ) -> AnalysisResults<'tcx, A> {
+  let (states, _) = iterate_all_locations(
+    body,
+    &location_domain,
+    &mut analysis,
+    &Budget::default(),
+    |_| 0,
+  );
+  AnalysisResults {
+    analysis,
+    location_domain,
+    states: States::AllLocations(states),
+    degraded_locations: None,
+  }
+}
+
+/// Like [`iterate_to_fixpoint`], but the `mode` determines how the results are stored,
+/// see [`EngineMode`], and the fixpoint stops early if it exceeds the `budget`.
+pub fn iterate_to_fixpoint_with_mode<'tcx, A: StatementEffect<'tcx> + 'tcx>(
+  _tcx: TyCtxt<'tcx>,
+  body: &'tcx Body<'tcx>,
+  location_domain: Rc<LocationOrArgDomain>,
+  mut analysis: A,
+  mode: EngineMode,
+  budget: &Budget,
+) -> AnalysisResults<'tcx, A>
+where
+  A::Domain: DiffDomain + DomainSize,
+  <A::Domain as DiffDomain>::Diff: 'tcx,
+{
+  let (states, unfinished) = match mode {
+    EngineMode::AllLocations => {
+      let (states, unfinished) = iterate_all_locations(
+        body,
+        &location_domain,
+        &mut analysis,
+        budget,
+        DomainSize::domain_size,
+      );
+      (States::AllLocations(states), unfinished)
+    }
+    EngineMode::Blocks | EngineMode::RowDeltas => {
+      let (exits, unfinished) = iterate_blocks(body, &mut analysis, budget);
+      let replay: Replay<'tcx, A> = match mode {
+        EngineMode::RowDeltas => {
+          let deltas = row_deltas(body, &analysis, &exits);
+          Box::new(move |_, state, location| {
+            state.apply_diff(&deltas[location.block][location.statement_index])
+          })
+        }
+        _ => Box::new(move |analysis, state, location| {
+          let statement =
+            &body.basic_blocks[location.block].statements[location.statement_index];
+          analysis.statement_effect(state, statement, location)
+        }),
+      };
+      let states = States::Blocks {
+        body,
+        exits,
+        replay,
+        last_block: RefCell::default(),
+      };
+      (states, unfinished)
+    }
+  };
+
+  let degraded_locations = (!unfinished.is_empty())
+    .then(|| reachable_locations(body, &location_domain, unfinished));
+
+  AnalysisResults {
+    analysis,
+    location_domain,
+    states,
+    degraded_locations,
+  }
+}
+
+/// Returns the rows that each statement of each block changes, given the states at the
+/// terminators of all blocks.
+fn row_deltas<'tcx, A: StatementEffect<'tcx>>(
+  body: &Body<'tcx>,
+  analysis: &A,
+  exits: &IndexVec<BasicBlock, Rc<A::Domain>>,
+) -> IndexVec<BasicBlock, Vec<<A::Domain as DiffDomain>::Diff>>
+where
+  A::Domain: DiffDomain,
+{
+  body
+    .basic_blocks
+    .iter_enumerated()
+    .map(|(block, data)| {
+      let mut state = block_entry(analysis, body, block, exits);
+      data
+        .statements
+        .iter()
+        .enumerate()
+        .map(|(statement_index, statement)| {
+          let prev = state.clone();
+          analysis.statement_effect(&mut state, statement, Location {
+            block,
+            statement_index,
+          });
+          state.diff(&prev)
+        })
+        .collect()
+    })
+    .collect()
+}
+
+/// Returns the locations that are reachable from the given locations, including themselves.
+fn reachable_locations(
+  body: &Body<'_>,
+  location_domain: &Rc<LocationOrArgDomain>,
+  mut stack: Vec<Location>,
+) -> LocationOrArgSet {
+  let mut reachable = LocationOrArgSet::new(location_domain);
+  while let Some(location) = stack.pop() {
+    if !reachable.insert(location) {
+      continue;
+    }
+    if location.statement_index < body.basic_blocks[location.block].statements.len() {
+      stack.push(location.successor_within_block());
+    } else {
+      stack.extend(
+        body
+          .basic_blocks
+          .successors(location.block)
+          .map(|block| Location {
+            block,
+            statement_index: 0,
+          }),
+      );
+    }
+  }
+  reachable
+}
+
+/// Runs the analysis to a fixpoint, storing the state at every location.
+///
+/// Also returns the locations that were not finished because the `budget` was exceeded.
+fn iterate_all_locations<'tcx, A: Analysis<'tcx>>(
+  body: &Body<'tcx>,
+  location_domain: &Rc<LocationOrArgDomain>,
+  analysis: &mut A,
+  budget: &Budget,
+  size_of: impl Fn(&A::Domain) -> usize,
+) -> (IndexVec<LocationOrArgIndex, Rc<A::Domain>>, Vec<Location>) {
   let bottom_value = analysis.bottom_value(body);
 
   // `state` materializes the analysis domain for *every* location, which is the crux
@@ -101,7 +466,7 @@ pub fn iterate_to_fixpoint<'tcx, A: Analysis<'tcx>>(
   let mut state = IndexVec::from_elem_n(bottom_value, num_locs);
 
   analysis
-    .initialize_start_block(body, &mut state[Location::START.to_index(&location_domain)]);
+    .initialize_start_block(body, &mut state[Location::START.to_index(location_domain)]);
 
   let mut dirty_queue: WorkQueue<LocationOrArgIndex> = WorkQueue::with_none(num_locs);
   if A::Direction::IS_FORWARD {
@@ -111,15 +476,29 @@ pub fn iterate_to_fixpoint<'tcx, A: Analysis<'tcx>>(
           block,
           statement_index,
         };
-        dirty_queue.insert(location.to_index(&location_domain));
+        dirty_queue.insert(location.to_index(location_domain));
       }
     }
   }
 
+  let location_of = |loc_index| match *location_domain.value(loc_index) {
+    LocationOrArg::Location(location) => location,
+    LocationOrArg::Arg(..) => unreachable!(),
+  };
+  let mut unfinished = Vec::new();
+  let mut iterations = 0;
+  let mut domain_size = 0;
   while let Some(loc_index) = dirty_queue.pop() {
-    let LocationOrArg::Location(location) = *location_domain.value(loc_index) else {
-      unreachable!()
-    };
+    if budget.is_exceeded(iterations, domain_size) {
+      unfinished.push(location_of(loc_index));
+      while let Some(loc_index) = dirty_queue.pop() {
+        unfinished.push(location_of(loc_index));
+      }
+      break;
+    }
+    iterations += 1;
+
+    let location = location_of(loc_index);
     let next_locs = match body.stmt_at(location) {
       Either::Left(statement) => {
         analysis.apply_statement_effect(&mut state[loc_index], statement, location);
@@ -137,6 +516,7 @@ pub fn iterate_to_fixpoint<'tcx, A: Analysis<'tcx>>(
           .collect::<Vec<_>>()
       }
     };
+    domain_size = size_of(&state[loc_index]);
 
     for next_loc in next_locs {
       let next_loc_index = location_domain.index(&LocationOrArg::Location(next_loc));
@@ -148,11 +528,69 @@ pub fn iterate_to_fixpoint<'tcx, A: Analysis<'tcx>>(
     }
   }
 
-  let state = state.into_iter().map(Rc::new).collect();
+  (state.into_iter().map(Rc::new).collect(), unfinished)
+}
 
-  AnalysisResults {
-    analysis,
-    location_domain,
-    state,
+/// Runs the analysis to a fixpoint, storing only the state at the terminator of every block.
+///
+/// Also returns the starts of the blocks that were not finished because the `budget`
+/// was exceeded.
+fn iterate_blocks<'tcx, A: Analysis<'tcx>>(
+  body: &Body<'tcx>,
+  analysis: &mut A,
+  budget: &Budget,
+) -> (IndexVec<BasicBlock, Rc<A::Domain>>, Vec<Location>)
+where
+  A::Domain: DomainSize,
+{
+  let bottom_value = analysis.bottom_value(body);
+  let num_blocks = body.basic_blocks.len();
+  let mut exits = IndexVec::from_elem_n(Rc::new(bottom_value), num_blocks);
+
+  let mut dirty_queue: WorkQueue<BasicBlock> = WorkQueue::with_none(num_blocks);
+  if A::Direction::IS_FORWARD {
+    for (block, _) in traversal::reverse_postorder(body) {
+      dirty_queue.insert(block);
+    }
+  }
+
+  let block_start = |block| Location {
+    block,
+    statement_index: 0,
+  };
+  let mut unfinished = Vec::new();
+  let mut iterations = 0;
+  let mut domain_size = 0;
+  while let Some(block) = dirty_queue.pop() {
+    if budget.is_exceeded(iterations, domain_size) {
+      unfinished.push(block_start(block));
+      while let Some(block) = dirty_queue.pop() {
+        unfinished.push(block_start(block));
+      }
+      break;
+    }
+    iterations += 1;
+
+    let mut state = block_entry(analysis, body, block, &exits);
+    let data = &body.basic_blocks[block];
+    for (statement_index, statement) in data.statements.iter().enumerate() {
+      let location = Location {
+        block,
+        statement_index,
+      };
+      analysis.apply_statement_effect(&mut state, statement, location);
+    }
+    let location = body.terminator_loc(block);
+    analysis.apply_terminator_effect(&mut state, data.terminator(), location);
+    domain_size = state.domain_size();
+
+    let changed = Rc::make_mut(&mut exits[block]).join(&state);
+    if changed {
+      for successor in body.basic_blocks.successors(block) {
+        dirty_queue.insert(successor);
+      }
+    }
   }
+
+  (exits, unfinished)
 }
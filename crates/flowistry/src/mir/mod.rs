@@ -1,10 +1,12 @@
 //! Infrastructure for analyzing MIR that supports the information flow analysis.
 
 use polonius_engine::FactTypes;
-use rustc_borrowck::consumers::{BodyWithBorrowckFacts, RustcFacts};
+use rustc_borrowck::consumers::{BodyWithBorrowckFacts, PoloniusInput, RustcFacts};
 use rustc_middle::mir::Body;
 
 pub mod aliases;
+pub mod budget;
+pub mod cross_crate;
 pub mod engine;
 pub mod placeinfo;
 pub mod utils;
@@ -31,6 +33,42 @@ impl<'tcx> FlowistryInput<'tcx, 'tcx> for &'tcx BodyWithBorrowckFacts<'tcx> {
     &self.body
   }
 
+  fn input_facts_subset_base(
+    self,
+  ) -> Box<
+    dyn Iterator<
+        Item = (
+          <RustcFacts as FactTypes>::Origin,
+          <RustcFacts as FactTypes>::Origin,
+        ),
+      > + 'tcx,
+  > {
+    SharedBody::new(self).input_facts_subset_base()
+  }
+}
+
+/// The parts of a [`BodyWithBorrowckFacts`] that the analysis reads, which unlike the
+/// rest of the borrow facts can be shared between threads.
+#[derive(Clone, Copy)]
+pub(crate) struct SharedBody<'tcx> {
+  body: &'tcx Body<'tcx>,
+  input_facts: &'tcx PoloniusInput,
+}
+
+impl<'tcx> SharedBody<'tcx> {
+  pub(crate) fn new(body_with_facts: &'tcx BodyWithBorrowckFacts<'tcx>) -> Self {
+    SharedBody {
+      body: &body_with_facts.body,
+      input_facts: body_with_facts.input_facts.as_ref().unwrap(),
+    }
+  }
+}
+
+impl<'tcx> FlowistryInput<'tcx, 'tcx> for SharedBody<'tcx> {
+  fn body(self) -> &'tcx Body<'tcx> {
+    self.body
+  }
+
   fn input_facts_subset_base(
     self,
   ) -> Box<
@@ -44,11 +82,9 @@ impl<'tcx> FlowistryInput<'tcx, 'tcx> for &'tcx BodyWithBorrowckFacts<'tcx> {
     Box::new(
       self
         .input_facts
-        .as_ref()
-        .unwrap()
         .subset_base
         .iter()
-        .map(|&(r1, r2, _)| (r1.into(), r2.into())),
+        .map(|&(r1, r2, _)| (r1, r2)),
     )
   }
 }
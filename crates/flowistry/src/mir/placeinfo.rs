@@ -24,7 +24,7 @@ use rustc_utils::{
 };
 
 use super::{aliases::Aliases, utils::PlaceSet, FlowistryInput};
-use crate::extensions::{is_extension_active, MutabilityMode};
+use crate::{extensions::MutabilityMode, infoflow::FlowConfig};
 
 /// Utilities for analyzing places: children, aliases, etc.
 pub struct PlaceInfo<'tcx> {
@@ -35,6 +35,7 @@ pub struct PlaceInfo<'tcx> {
 
   // Core computed data structure
   aliases: Aliases<'tcx>,
+  pub(crate) config: Rc<FlowConfig>,
 
   // Caching for derived analysis
   normalized_cache: CopyCache<Place<'tcx>, Place<'tcx>>,
@@ -64,14 +65,26 @@ impl<'tcx> PlaceInfo<'tcx> {
     tcx: TyCtxt<'tcx>,
     def_id: DefId,
     input: impl FlowistryInput<'tcx, 'a>,
+  ) -> Self {
+    Self::build_with_config(tcx, def_id, input, Rc::default())
+  }
+
+  /// Computes all the metadata about places used within the infoflow analysis,
+  /// with the precision levers and call handling given by `config`.
+  pub fn build_with_config<'a>(
+    tcx: TyCtxt<'tcx>,
+    def_id: DefId,
+    input: impl FlowistryInput<'tcx, 'a>,
+    config: Rc<FlowConfig>,
   ) -> Self {
     block_timer!("aliases");
     let body = input.body();
     let location_domain = Self::build_location_arg_domain(body);
-    let aliases = Aliases::build(tcx, def_id, input);
+    let aliases = Aliases::build(tcx, def_id, input, &config.eval_mode, &config.budget);
 
     PlaceInfo {
       aliases,
+      config,
       tcx,
       body,
       def_id,
@@ -83,6 +96,17 @@ impl<'tcx> PlaceInfo<'tcx> {
     }
   }
 
+  /// Returns true if the alias analysis exceeded the budget of the configuration,
+  /// see [`Aliases::is_degraded`].
+  pub fn is_degraded(&self) -> bool {
+    self.aliases.is_degraded()
+  }
+
+  /// Returns the configuration of the analysis that uses these places.
+  pub fn config(&self) -> &FlowConfig {
+    &self.config
+  }
+
   /// Normalizes a place via [`PlaceExt::normalize`] (cached).
   ///
   /// See the `PlaceExt` documentation for details on how normalization works.
@@ -143,8 +167,7 @@ impl<'tcx> PlaceInfo<'tcx> {
         .into_iter()
         .chain([place])
         .filter(|place| {
-          if let Some((place, _)) = place.refs_in_projection().last()
-          {
+          if let Some((place, _)) = place.refs_in_projection().last() {
             let ty = place.ty(self.body.local_decls(), self.tcx).ty;
             if ty.is_box() || ty.is_unsafe_ptr() {
               return true;
@@ -159,6 +182,7 @@ impl<'tcx> PlaceInfo<'tcx> {
   fn collect_loans(&self, ty: Ty<'tcx>, mutability: Mutability) -> PlaceSet<'tcx> {
     let mut collector = LoanCollector {
       aliases: &self.aliases,
+      ignore_mut: self.config.eval_mode.mutability_mode == MutabilityMode::IgnoreMut,
       unknown_region: Region::new_var(self.tcx, UNKNOWN_REGION),
       target_mutability: mutability,
       stack: vec![],
@@ -200,6 +224,7 @@ impl<'tcx> PlaceInfo<'tcx> {
 // Can we consolidate these?
 struct LoanCollector<'a, 'tcx> {
   aliases: &'a Aliases<'tcx>,
+  ignore_mut: bool,
   unknown_region: Region<'tcx>,
   target_mutability: Mutability,
   stack: Vec<Mutability>,
@@ -240,8 +265,7 @@ impl<'tcx> TypeVisitor<TyCtxt<'tcx>> for LoanCollector<'_, 'tcx> {
     };
     if let Some(loans) = self.aliases.loans.get(&region) {
       let under_immut_ref = self.stack.iter().any(|m| *m == Mutability::Not);
-      let ignore_mut =
-        is_extension_active(|mode| mode.mutability_mode == MutabilityMode::IgnoreMut);
+      let ignore_mut = self.ignore_mut;
       self
         .loans
         .extend(loans.iter().filter_map(|(place, mutability)| {
@@ -8,7 +8,7 @@ use rustc_middle::{
 };
 use rustc_utils::{BodyExt, OperandExt, PlaceExt};
 
-use crate::extensions::{is_extension_active, MutabilityMode};
+use crate::extensions::{EvalMode, MutabilityMode};
 
 /// An unordered collections of MIR [`Place`]s.
 ///
@@ -23,9 +23,9 @@ pub fn arg_mut_ptrs<'tcx>(
   tcx: TyCtxt<'tcx>,
   body: &Body<'tcx>,
   def_id: DefId,
+  eval_mode: &EvalMode,
 ) -> Vec<(usize, Place<'tcx>)> {
-  let ignore_mut =
-    is_extension_active(|mode| mode.mutability_mode == MutabilityMode::IgnoreMut);
+  let ignore_mut = eval_mode.mutability_mode == MutabilityMode::IgnoreMut;
   args
     .iter()
     .flat_map(|(i, place)| {
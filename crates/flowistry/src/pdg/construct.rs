@@ -0,0 +1,648 @@
+//! Construction of the PDG from the modular mutations computed by [`FlowAnalysis`].
+
+use std::rc::Rc;
+
+use either::Either;
+use flowistry_pdg::{CallString, GlobalLocation, RichLocation};
+use indexical::impls::RustcIndexMatrix as IndexMatrix;
+use log::debug;
+use petgraph::graph::{DiGraph, NodeIndex};
+use rustc_data_structures::fx::{FxHashMap as HashMap, FxHashSet as HashSet};
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::{
+  mir::{visit::Visitor, *},
+  ty::{TyCtxt, TyKind},
+};
+use rustc_mir_dataflow::{Analysis, AnalysisDomain, JoinSemiLattice};
+use rustc_utils::{
+  mir::{borrowck_facts::get_body_with_borrowck_facts, location_or_arg::LocationOrArg},
+  BodyExt, OperandExt, PlaceExt,
+};
+
+use super::graph::{DepEdge, DepNode};
+use crate::{
+  extensions::ContextMode,
+  infoflow::{
+    mutation::{ModularMutationVisitor, Mutation},
+    FlowAnalysis, FlowConfig,
+  },
+  mir::{
+    budget::Budget,
+    engine::{self, AnalysisResults, StatementEffect},
+    placeinfo::PlaceInfo,
+    utils,
+  },
+};
+
+/// Top-level parameters to PDG construction.
+#[derive(Clone)]
+pub struct PdgParams<'tcx> {
+  tcx: TyCtxt<'tcx>,
+  root: LocalDefId,
+  context_mode: ContextMode,
+  config: Rc<FlowConfig>,
+}
+
+impl<'tcx> PdgParams<'tcx> {
+  /// Must provide the [`TyCtxt`] and the [`LocalDefId`] of the function that is the root of the PDG.
+  ///
+  /// By default, calls are handled by the modular approximation ([`ContextMode::SigOnly`]).
+  pub fn new(tcx: TyCtxt<'tcx>, root: LocalDefId) -> Self {
+    PdgParams {
+      tcx,
+      root,
+      context_mode: ContextMode::SigOnly,
+      config: Rc::default(),
+    }
+  }
+
+  /// Sets the configuration used for the modular approximation of each body, e.g. the
+  /// summaries and call models for calls that are not inlined.
+  ///
+  /// Calls are inlined according to [`PdgParams::with_context_mode`], not the
+  /// `context_mode` of the configuration.
+  pub fn with_config(self, config: FlowConfig) -> Self {
+    PdgParams {
+      config: Rc::new(config),
+      ..self
+    }
+  }
+
+  /// Sets how calls are handled during construction.
+  ///
+  /// With [`ContextMode::Recurse`], the body of each local callee is inlined into the PDG
+  /// at its call site. Every node of the callee is keyed by the full [`CallString`]
+  /// leading to it, so each call site of a function gets a distinct subgraph.
+  pub fn with_context_mode(self, context_mode: ContextMode) -> Self {
+    PdgParams {
+      context_mode,
+      ..self
+    }
+  }
+}
+
+/// For each place, the locations that last mutated it.
+type LastMutationsDomain<'tcx> = IndexMatrix<Place<'tcx>, LocationOrArg>;
+
+/// Dataflow analysis that computes the locations which last mutated each place.
+///
+/// The domain maps each place to the locations that most recently mutated it, rather than
+/// to all of its transitive dependencies like a [`FlowDomain`](crate::infoflow::FlowDomain).
+/// Mutations, aliases and strong updates are interpreted exactly as in [`FlowAnalysis`].
+struct LastMutations<'tcx> {
+  flow: FlowAnalysis<'tcx>,
+}
+
+impl<'tcx> LastMutations<'tcx> {
+  fn apply_mutations(
+    &self,
+    state: &mut LastMutationsDomain<'tcx>,
+    mutations: &[Mutation<'tcx>],
+    location: Location,
+  ) {
+    let place_info = &self.flow.place_info;
+    for mt in mutations {
+      if self.flow.is_strong_update(mt) {
+        for sub in place_info.children(mt.mutated).iter() {
+          state.clear_row(&place_info.normalize(*sub));
+        }
+      }
+
+      for alias in self.flow.mutable_aliases(mt.mutated) {
+        state.insert(place_info.normalize(alias), location);
+      }
+    }
+  }
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for LastMutations<'tcx> {
+  type Domain = LastMutationsDomain<'tcx>;
+  const NAME: &'static str = "LastMutations";
+
+  fn bottom_value(&self, _body: &Body<'tcx>) -> Self::Domain {
+    IndexMatrix::new(self.flow.location_domain())
+  }
+
+  fn initialize_start_block(&self, _body: &Body<'tcx>, state: &mut Self::Domain) {
+    for (place, loc) in self.flow.initial_rows() {
+      state.insert(place, loc);
+    }
+  }
+}
+
+impl<'tcx> StatementEffect<'tcx> for LastMutations<'tcx> {
+  fn statement_effect(
+    &self,
+    state: &mut Self::Domain,
+    statement: &Statement<'tcx>,
+    location: Location,
+  ) {
+    ModularMutationVisitor::new(&self.flow.place_info, |_, mutations| {
+      self.apply_mutations(state, &mutations, location)
+    })
+    .visit_statement(statement, location);
+  }
+}
+
+impl<'tcx> Analysis<'tcx> for LastMutations<'tcx> {
+  fn apply_statement_effect(
+    &mut self,
+    state: &mut Self::Domain,
+    statement: &Statement<'tcx>,
+    location: Location,
+  ) {
+    self.statement_effect(state, statement, location);
+  }
+
+  fn apply_terminator_effect<'mir>(
+    &mut self,
+    state: &mut Self::Domain,
+    terminator: &'mir Terminator<'tcx>,
+    location: Location,
+  ) -> TerminatorEdges<'mir, 'tcx> {
+    ModularMutationVisitor::new(&self.flow.place_info, |_, mutations| {
+      self.apply_mutations(state, &mutations, location)
+    })
+    .visit_terminator(terminator, location);
+    terminator.edges()
+  }
+
+  fn apply_call_return_effect(
+    &mut self,
+    _state: &mut Self::Domain,
+    _block: BasicBlock,
+    _return_places: CallReturnPlaces<'_, 'tcx>,
+  ) {
+  }
+}
+
+/// The graph under construction, shared between the bodies that contribute to it.
+#[derive(Default)]
+pub(super) struct GraphBuilder<'tcx> {
+  pub(super) graph: DiGraph<DepNode<'tcx>, DepEdge>,
+  nodes: HashMap<DepNode<'tcx>, NodeIndex>,
+  nodes_by_call_string: HashMap<CallString, Vec<NodeIndex>>,
+  edges: HashSet<(NodeIndex, NodeIndex, DepEdge)>,
+  results: HashMap<LocalDefId, Rc<AnalysisResults<'tcx, LastMutations<'tcx>>>>,
+}
+
+impl<'tcx> GraphBuilder<'tcx> {
+  fn add_node(&mut self, node: DepNode<'tcx>) -> NodeIndex {
+    *self.nodes.entry(node).or_insert_with(|| {
+      let index = self.graph.add_node(node);
+      self
+        .nodes_by_call_string
+        .entry(node.at)
+        .or_default()
+        .push(index);
+      index
+    })
+  }
+
+  fn add_edge(&mut self, src: NodeIndex, dst: NodeIndex, edge: DepEdge) {
+    if self.edges.insert((src, dst, edge)) {
+      self.graph.add_edge(src, dst, edge);
+    }
+  }
+
+  /// Returns every node located at exactly `at`.
+  fn nodes_at(&self, at: CallString) -> Vec<NodeIndex> {
+    self
+      .nodes_by_call_string
+      .get(&at)
+      .cloned()
+      .unwrap_or_default()
+  }
+}
+
+/// The nodes through which an inlined callee is connected to its caller.
+struct CalleeBoundary<'tcx> {
+  /// The callee's argument nodes, each with the caller nodes that flow into it.
+  inputs: Vec<(NodeIndex, Vec<NodeIndex>)>,
+
+  /// The callee's output nodes, each with a caller place that it may mutate.
+  outputs: Vec<(Place<'tcx>, NodeIndex)>,
+}
+
+/// Adds the nodes and edges for a single body to a [`GraphBuilder`].
+pub(super) struct GraphConstructor<'tcx> {
+  params: PdgParams<'tcx>,
+  tcx: TyCtxt<'tcx>,
+  def_id: LocalDefId,
+  body: &'tcx Body<'tcx>,
+  call_string_prefix: Option<CallString>,
+  results: Rc<AnalysisResults<'tcx, LastMutations<'tcx>>>,
+}
+
+impl<'tcx> GraphConstructor<'tcx> {
+  /// Creates a [`GraphConstructor`] for the root function of the PDG.
+  pub(super) fn root(params: &PdgParams<'tcx>, builder: &mut GraphBuilder<'tcx>) -> Self {
+    Self::new(params.clone(), params.root, None, builder)
+  }
+
+  /// Creates a [`GraphConstructor`] for `def_id`, whose call strings are all extensions
+  /// of `call_string_prefix`, i.e. the call site at which it is inlined.
+  fn new(
+    params: PdgParams<'tcx>,
+    def_id: LocalDefId,
+    call_string_prefix: Option<CallString>,
+    builder: &mut GraphBuilder<'tcx>,
+  ) -> Self {
+    let tcx = params.tcx;
+    let body_with_facts = get_body_with_borrowck_facts(tcx, def_id);
+    let body = &body_with_facts.body;
+
+    let results = builder
+      .results
+      .entry(def_id)
+      .or_insert_with(|| {
+        debug!("{}", body.to_string(tcx).unwrap());
+        let place_info = PlaceInfo::build_with_config(
+          tcx,
+          def_id.to_def_id(),
+          body_with_facts,
+          params.config.clone(),
+        );
+        let location_domain = place_info.location_domain().clone();
+        let flow = FlowAnalysis::new(tcx, def_id.to_def_id(), body, place_info);
+        Rc::new(engine::iterate_to_fixpoint_with_mode(
+          tcx,
+          body,
+          location_domain,
+          LastMutations { flow },
+          params.config.engine_mode,
+          // The graph needs the complete results, so only the alias analysis is limited
+          &Budget::default(),
+        ))
+      })
+      .clone();
+
+    GraphConstructor {
+      params,
+      tcx,
+      def_id,
+      body,
+      call_string_prefix,
+      results,
+    }
+  }
+
+  fn flow(&self) -> &FlowAnalysis<'tcx> {
+    &self.results.analysis.flow
+  }
+
+  fn make_call_string(&self, location: impl Into<RichLocation>) -> CallString {
+    let location = GlobalLocation {
+      function: self.def_id,
+      location: location.into(),
+    };
+    match self.call_string_prefix {
+      Some(prefix) => prefix.push(location),
+      None => CallString::single(location),
+    }
+  }
+
+  fn make_node(
+    &self,
+    builder: &mut GraphBuilder<'tcx>,
+    place: Place<'tcx>,
+    location: impl Into<RichLocation>,
+  ) -> NodeIndex {
+    let at = self.make_call_string(location);
+    builder.add_node(DepNode::new(place, at, self.tcx, self.body))
+  }
+
+  /// Returns the nodes for the last mutations to any of `places` in `state`.
+  fn last_mutations(
+    &self,
+    builder: &mut GraphBuilder<'tcx>,
+    state: &LastMutationsDomain<'tcx>,
+    places: impl IntoIterator<Item = Place<'tcx>>,
+  ) -> Vec<NodeIndex> {
+    let place_info = &self.flow().place_info;
+    let rows = places
+      .into_iter()
+      .map(|place| place_info.normalize(place))
+      .collect::<HashSet<_>>();
+    rows
+      .into_iter()
+      .flat_map(|row| {
+        state
+          .row(&row)
+          .map(move |loc| match loc {
+            LocationOrArg::Location(loc) => (row, RichLocation::Location(*loc)),
+            LocationOrArg::Arg(_) => (row, RichLocation::Start),
+          })
+          .collect::<Vec<_>>()
+      })
+      .map(|(place, location)| self.make_node(builder, place, location))
+      .collect()
+  }
+
+  /// Returns the nodes for the last mutations to every place that influences `place`.
+  fn input_nodes(
+    &self,
+    builder: &mut GraphBuilder<'tcx>,
+    state: &LastMutationsDomain<'tcx>,
+    place: Place<'tcx>,
+  ) -> Vec<NodeIndex> {
+    self.last_mutations(builder, state, self.flow().influences(place))
+  }
+
+  /// Returns the nodes for the discriminants of switches that `block` is control-dependent on.
+  fn control_nodes(
+    &self,
+    builder: &mut GraphBuilder<'tcx>,
+    block: BasicBlock,
+  ) -> Vec<(NodeIndex, Location)> {
+    let controlled_by = self.flow().control_dependencies.dependent_on(block);
+    controlled_by
+      .into_iter()
+      .flat_map(|set| set.iter())
+      .filter_map(|block| {
+        let terminator = self.body.basic_blocks[block].terminator();
+        let TerminatorKind::SwitchInt { discr, .. } = &terminator.kind else {
+          return None;
+        };
+        let discr_place = discr.as_place()?;
+        let location = self.body.terminator_loc(block);
+        let place = self.flow().place_info.normalize(discr_place);
+        Some((self.make_node(builder, place, location), location))
+      })
+      .collect()
+  }
+
+  /// Adds nodes and edges for every mutation (or branch) at `location`.
+  fn handle_location(&self, builder: &mut GraphBuilder<'tcx>, location: Location) {
+    let state = self.results.state_before(self.body, location);
+    let at = self.make_call_string(location);
+    let control_inputs = self.control_nodes(builder, location.block);
+
+    let add_inputs = |builder: &mut GraphBuilder<'tcx>, target, inputs: &[NodeIndex]| {
+      for input in inputs {
+        builder.add_edge(*input, target, DepEdge::data(at));
+      }
+      for (ctrl, ctrl_loc) in &control_inputs {
+        builder.add_edge(
+          *ctrl,
+          target,
+          DepEdge::control(self.make_call_string(*ctrl_loc)),
+        );
+      }
+    };
+
+    if let Either::Right(Terminator {
+      kind: TerminatorKind::SwitchInt { discr, .. },
+      ..
+    }) = self.body.stmt_at(location)
+    {
+      if let Some(discr_place) = discr.as_place() {
+        let place = self.flow().place_info.normalize(discr_place);
+        let target = self.make_node(builder, place, location);
+        let inputs = self.input_nodes(builder, &state, discr_place);
+        add_inputs(builder, target, &inputs);
+      }
+      return;
+    }
+
+    // If the callee is inlined, then its outputs replace the modular approximation
+    // of how the arguments flow into the mutated places.
+    let callee = match self.body.stmt_at(location) {
+      Either::Right(Terminator {
+        kind:
+          TerminatorKind::Call {
+            func,
+            args,
+            destination,
+            ..
+          },
+        ..
+      }) => self.inline_call(builder, location, &state, func, args, *destination),
+      _ => None,
+    };
+    if let Some(callee) = &callee {
+      for (start, inputs) in &callee.inputs {
+        add_inputs(builder, *start, inputs);
+      }
+    }
+
+    let mut mutations = Vec::new();
+    ModularMutationVisitor::new(&self.flow().place_info, |_, mts| mutations.extend(mts))
+      .visit_location(self.body, location);
+
+    for mt in &mutations {
+      let mut inputs = match callee {
+        Some(_) => Vec::new(),
+        None => mt
+          .inputs
+          .iter()
+          .flat_map(|input| self.input_nodes(builder, &state, *input))
+          .collect::<Vec<_>>(),
+      };
+
+      // If the mutated place is indirect, then it depends on its provenance
+      let provenance = self
+        .flow()
+        .provenance(mt.mutated)
+        .into_iter()
+        .flat_map(|place| self.flow().place_info.conflicts(place).iter().copied())
+        .collect::<Vec<_>>();
+      inputs.extend(self.last_mutations(builder, &state, provenance));
+
+      let strong_update = self.flow().is_strong_update(mt);
+      for alias in self.flow().mutable_aliases(mt.mutated) {
+        let place = self.flow().place_info.normalize(alias);
+        let target = self.make_node(builder, place, location);
+        add_inputs(builder, target, &inputs);
+
+        if let Some(callee) = &callee {
+          let conflicts = self.flow().place_info.conflicts(alias);
+          let outputs = callee
+            .outputs
+            .iter()
+            .filter(|(output, _)| {
+              conflicts
+                .iter()
+                .any(|c| self.flow().place_info.normalize(*c) == *output)
+            })
+            .map(|(_, node)| *node)
+            .collect::<Vec<_>>();
+          add_inputs(builder, target, &outputs);
+        }
+
+        // A weak update preserves the previous value of the place
+        if !strong_update {
+          let conflicts = self.flow().place_info.conflicts(alias).clone();
+          let prev = self.last_mutations(builder, &state, conflicts);
+          add_inputs(builder, target, &prev);
+        }
+      }
+    }
+  }
+
+  /// Returns the callee of a call to `func` if it should be inlined.
+  ///
+  /// Only local functions with a body are inlined. As with [`FlowAnalysis`]'s recursive
+  /// mode, calls are not inlined if the callee diverges, contains unsafe code,
+  /// takes mutable closures as input, or is already on the call string.
+  fn inlineable_callee(
+    &self,
+    func: &Operand<'tcx>,
+    arg_places: &[(usize, Place<'tcx>)],
+  ) -> Option<LocalDefId> {
+    let tcx = self.tcx;
+    let TyKind::FnDef(def_id, _) = func.constant()?.const_.ty().kind() else {
+      debug!("  Func is not a constant FnDef");
+      return None;
+    };
+
+    let Some(callee) = def_id.as_local() else {
+      debug!("  Func is not in local crate");
+      return None;
+    };
+
+    if tcx.hir().maybe_body_owned_by(callee).is_none() {
+      debug!("  Func does not have a body");
+      return None;
+    }
+
+    // The body of a trait method is only a default, which may be overridden
+    if tcx.trait_of_item(*def_id).is_some() {
+      debug!("  Func is a trait method");
+      return None;
+    }
+
+    let fn_sig = tcx.fn_sig(*def_id);
+    if fn_sig.skip_binder().output().skip_binder().is_never() {
+      debug!("  Func returns never");
+      return None;
+    }
+
+    let unsafety = tcx.mir_unsafety_check_result(callee);
+    if !unsafety.used_unsafe_blocks.is_empty() {
+      debug!("  Func contains unsafe blocks");
+      return None;
+    }
+
+    if self.flow().has_mutable_closure_inputs(arg_places) {
+      debug!("  Func has closure inputs");
+      return None;
+    }
+
+    let recursive = callee == self.def_id
+      || self
+        .call_string_prefix
+        .is_some_and(|prefix| prefix.iter().any(|loc| loc.function == callee));
+    if recursive {
+      debug!("  Func is a recursive call");
+      return None;
+    }
+
+    Some(callee)
+  }
+
+  /// Inlines the PDG of the callee of the call at `location` into `builder`, if possible.
+  ///
+  /// Returns the edges that connect the callee's subgraph to the caller.
+  fn inline_call(
+    &self,
+    builder: &mut GraphBuilder<'tcx>,
+    location: Location,
+    state: &LastMutationsDomain<'tcx>,
+    func: &Operand<'tcx>,
+    args: &[Operand<'tcx>],
+    destination: Place<'tcx>,
+  ) -> Option<CalleeBoundary<'tcx>> {
+    if self.params.context_mode != ContextMode::Recurse {
+      return None;
+    }
+
+    debug!("Checking whether can inline {func:?}");
+    let arg_places = utils::arg_places(args);
+    let callee = self.inlineable_callee(func, &arg_places)?;
+
+    let call_string = self.make_call_string(location);
+    let child =
+      GraphConstructor::new(self.params.clone(), callee, Some(call_string), builder);
+    child.construct_into(builder);
+
+    let translate = |place: Place<'tcx>, mutated: bool| {
+      self.flow().translate_child_to_parent(
+        child.body,
+        &arg_places,
+        destination,
+        place,
+        mutated,
+      )
+    };
+    let place_of =
+      |builder: &GraphBuilder<'tcx>, node: NodeIndex| builder.graph[node].place;
+
+    let inputs = builder
+      .nodes_at(child.make_call_string(RichLocation::Start))
+      .into_iter()
+      .filter_map(|start| {
+        let parent = translate(place_of(builder, start), false)?;
+        Some((start, self.input_nodes(builder, state, parent)))
+      })
+      .collect();
+
+    let outputs = builder
+      .nodes_at(child.make_call_string(RichLocation::End))
+      .into_iter()
+      .filter_map(|end| Some((end, translate(place_of(builder, end), true)?)))
+      .flat_map(|(end, parent)| {
+        self
+          .flow()
+          .mutable_aliases(parent)
+          .into_iter()
+          .map(move |alias| (self.flow().place_info.normalize(alias), end))
+      })
+      .collect();
+
+    Some(CalleeBoundary { inputs, outputs })
+  }
+
+  /// Adds nodes for the outputs of the body: the return place, and
+  /// every mutated place reachable from an argument.
+  fn handle_return(&self, builder: &mut GraphBuilder<'tcx>) {
+    let analysis = &self.results.analysis;
+    let mut exit_state = analysis.bottom_value(self.body);
+    for loc in self.body.all_returns() {
+      exit_state.join(&self.results.shared_state_at(loc));
+    }
+
+    let at = self.make_call_string(RichLocation::End);
+    let return_place = Place::return_place();
+    let return_node = self.make_node(builder, return_place, RichLocation::End);
+    for input in self.input_nodes(builder, &exit_state, return_place) {
+      builder.add_edge(input, return_node, DepEdge::data(at));
+    }
+
+    let mutated_args = exit_state
+      .rows()
+      .filter(|(place, locs)| {
+        place.is_arg(self.body)
+          && place.is_indirect()
+          && locs
+            .iter()
+            .any(|loc| matches!(loc, LocationOrArg::Location(_)))
+      })
+      .map(|(place, _)| *place)
+      .collect::<Vec<_>>();
+    for place in mutated_args {
+      let node = self.make_node(builder, place, RichLocation::End);
+      for input in self.last_mutations(builder, &exit_state, [place]) {
+        builder.add_edge(input, node, DepEdge::data(at));
+      }
+    }
+  }
+
+  /// Adds all the nodes and edges for this body to `builder`.
+  pub(super) fn construct_into(&self, builder: &mut GraphBuilder<'tcx>) {
+    for location in self.body.all_locations() {
+      self.handle_location(builder, location);
+    }
+    self.handle_return(builder);
+  }
+}
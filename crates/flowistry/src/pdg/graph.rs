@@ -0,0 +1,170 @@
+//! The representation of the PDG.
+
+use std::{fmt, path::Path};
+
+pub use flowistry_pdg::graph::{DepEdge, DepEdgeKind, DepNodeKind};
+use flowistry_pdg::{graph as portable, rustc_proxies, CallString, RichLocation};
+use internment::Intern;
+use petgraph::{dot, graph::DiGraph};
+use rustc_middle::{
+  mir::{Body, Place, TerminatorKind, RETURN_PLACE},
+  ty::TyCtxt,
+};
+use rustc_span::Span;
+use rustc_utils::{mir::body::run_dot, PlaceExt};
+
+/// A node in the program dependency graph.
+///
+/// Represents a place at a particular call-string.
+/// The place is in the body of the leaf of the call-string.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct DepNode<'tcx> {
+  /// A place in memory in a particular body.
+  pub place: Place<'tcx>,
+
+  /// The point in the execution of the program.
+  pub at: CallString,
+
+  /// The role of the place in its body.
+  kind: DepNodeKind,
+
+  /// The span of the instruction at [`DepNode::at`], or the declaration of
+  /// the place if the node is at the start or end of the body.
+  span: Span,
+
+  /// Pretty representation of the place.
+  /// This is cached as an interned string on [`DepNode`] because to compute it later,
+  /// we would have to regenerate the entire monomorphized body for a given place.
+  place_pretty: Option<Intern<String>>,
+}
+
+impl<'tcx> DepNode<'tcx> {
+  /// Constructs a new [`DepNode`].
+  ///
+  /// The `tcx` and `body` arguments are used to precompute a pretty string
+  /// representation of the [`DepNode`].
+  pub fn new(
+    place: Place<'tcx>,
+    at: CallString,
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+  ) -> Self {
+    DepNode {
+      place,
+      at,
+      kind: Self::compute_kind(place, at, body),
+      span: match at.leaf().location {
+        RichLocation::Location(location) => body.source_info(location).span,
+        RichLocation::Start | RichLocation::End => {
+          body.local_decls[place.local].source_info.span
+        }
+      },
+      place_pretty: place.to_string(tcx, body).map(Intern::new),
+    }
+  }
+
+  fn compute_kind(place: Place<'tcx>, at: CallString, body: &Body<'tcx>) -> DepNodeKind {
+    if place.local == RETURN_PLACE {
+      return DepNodeKind::Return;
+    }
+    if place.is_arg(body) {
+      return DepNodeKind::Argument(place.local.as_usize() - 1);
+    }
+    let is_call = match at.leaf().location {
+      RichLocation::Location(location) => matches!(
+        body
+          .stmt_at(location)
+          .right()
+          .map(|terminator| &terminator.kind),
+        Some(TerminatorKind::Call { .. })
+      ),
+      RichLocation::Start | RichLocation::End => false,
+    };
+    if is_call {
+      DepNodeKind::CallSite
+    } else {
+      DepNodeKind::Local
+    }
+  }
+}
+
+impl DepNode<'_> {
+  /// Returns a pretty string representation of the place, if one exists.
+  pub fn place_pretty(&self) -> Option<&str> {
+    self.place_pretty.map(|s| s.as_ref().as_str())
+  }
+
+  /// Returns the role of the place in its body.
+  pub fn kind(&self) -> DepNodeKind {
+    self.kind
+  }
+
+  /// Returns the span of the source code that the node represents.
+  pub fn span(&self) -> Span {
+    self.span
+  }
+
+  /// Converts this node into its portable representation, using `tcx` to resolve its span.
+  pub fn to_portable(&self, tcx: TyCtxt) -> portable::DepNode {
+    portable::DepNode {
+      place: self.place.into(),
+      place_pretty: self.place_pretty().map(str::to_owned),
+      span: rustc_proxies::Span::from_rustc(self.span, tcx.sess.source_map()),
+      kind: self.kind,
+      at: self.at,
+    }
+  }
+}
+
+impl fmt::Display for DepNode<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.place_pretty() {
+      Some(s) => s.fmt(f)?,
+      None => write!(f, "{:?}", self.place)?,
+    };
+    write!(f, " @ {}", self.at)
+  }
+}
+
+/// The top-level PDG.
+#[derive(Clone, Debug)]
+pub struct DepGraph<'tcx> {
+  /// The petgraph representation of the PDG.
+  pub graph: DiGraph<DepNode<'tcx>, DepEdge>,
+}
+
+impl<'tcx> DepGraph<'tcx> {
+  /// Constructs a new [`DepGraph`].
+  pub fn new(graph: DiGraph<DepNode<'tcx>, DepEdge>) -> Self {
+    Self { graph }
+  }
+
+  /// Generates a graphviz visualization of the PDG and saves it to `path`.
+  pub fn generate_graphviz(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let graph_dot = format!(
+      "{}",
+      dot::Dot::with_attr_getters(
+        &self.graph,
+        &[],
+        &|_, _| "fontname=\"Courier New\"".to_string(),
+        &|_, (_, _)| "fontname=\"Courier New\",shape=box".to_string(),
+      )
+    );
+    run_dot(path.as_ref(), graph_dot.into_bytes())
+  }
+
+  /// Converts the PDG into its portable representation, which can be serialized
+  /// and loaded without depending on rustc.
+  ///
+  /// `tcx` must be the context that created the PDG, and is used to resolve spans.
+  pub fn to_portable(&self, tcx: TyCtxt<'tcx>) -> portable::DepGraph {
+    let mut graph = portable::DepGraph::new();
+    for node in self.graph.node_weights() {
+      graph.add_node(node.to_portable(tcx));
+    }
+    for edge in self.graph.raw_edges() {
+      graph.add_edge(edge.source().index(), edge.target().index(), edge.weight);
+    }
+    graph
+  }
+}
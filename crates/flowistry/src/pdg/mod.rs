@@ -0,0 +1,32 @@
+//! Compute program dependence graphs (PDG) for a function.
+//!
+//! The main function is [`compute_pdg`]. A PDG is a graph whose nodes are [`Place`](rustc_middle::mir::Place)s
+//! at a particular [`CallString`], and whose edges are data or control dependencies between
+//! those places. The dependencies are derived from the same modular approximation of mutations and
+//! aliasing that is used by the [information flow analysis](crate::infoflow::FlowAnalysis).
+//! Unlike a [`FlowDomain`](crate::infoflow::FlowDomain), which contains the transitive dependencies
+//! of each place, the PDG only contains the direct dependencies between operations.
+
+pub use flowistry_pdg::{CallString, GlobalLocation, RichLocation};
+
+use self::construct::{GraphBuilder, GraphConstructor};
+pub use self::{
+  construct::PdgParams,
+  graph::{DepEdge, DepEdgeKind, DepGraph, DepNode, DepNodeKind},
+};
+
+mod construct;
+mod graph;
+
+/// Computes the program dependence graph (PDG) of the function specified by `params`.
+///
+/// The resulting [`DepGraph`] contains a node for every place mutated within the body
+/// (keyed by the location of the mutation), a node for every argument place at
+/// [`RichLocation::Start`], and nodes for the outputs of the function at [`RichLocation::End`].
+/// If calls are inlined (see [`PdgParams::with_context_mode`]), then the graph also contains
+/// the nodes of each callee, keyed by the [`CallString`] of the call site.
+pub fn compute_pdg<'tcx>(params: PdgParams<'tcx>) -> DepGraph<'tcx> {
+  let mut builder = GraphBuilder::default();
+  GraphConstructor::root(&params, &mut builder).construct_into(&mut builder);
+  DepGraph::new(builder.graph)
+}
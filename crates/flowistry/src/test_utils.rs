@@ -2,29 +2,32 @@
 
 #![allow(missing_docs)]
 
-use std::{fs, io, panic, path::Path};
+use std::{fs, io, panic, path::Path, process::Command};
 
 use anyhow::Result;
-use fluid_let::fluid_set;
 use log::info;
 use rustc_borrowck::consumers::BodyWithBorrowckFacts;
 use rustc_data_structures::fx::FxHashSet as HashSet;
 use rustc_hir::BodyId;
-use rustc_middle::ty::TyCtxt;
+use rustc_middle::{
+  mir::{Body, Location, Place, StatementKind},
+  ty::TyCtxt,
+};
 use rustc_span::Span;
 pub use rustc_utils::test_utils::{compare_ranges, fmt_ranges, parse_ranges};
 use rustc_utils::{
-  mir::borrowck_facts,
+  mir::{borrowck_facts, location_or_arg::LocationOrArg},
   source_map::{
     range::{ByteRange, CharPos, ToSpan},
     spanner::Spanner,
   },
-  test_utils,
+  test_utils::{self, StringLoader, DUMMY_FILE_NAME},
+  BodyExt,
 };
 
 use crate::{
-  extensions::{ContextMode, EvalMode, MutabilityMode, PointerMode, EVAL_MODE},
-  infoflow,
+  extensions::{ContextMode, CrossCrateMode, MutabilityMode, PointerMode, UnsafeMode},
+  infoflow::{self, summaries::CallSummaries, FlowConfig, TrackedKinds},
 };
 
 pub fn compile_body_with_range(
@@ -37,6 +40,28 @@ pub fn compile_body_with_range(
   test_utils::compile_body_with_range(input, compute_target, callback)
 }
 
+/// Compiles `input` with its target range delimited by `` `( `` and `` )` ``, and calls
+/// `callback` with the body that contains the target.
+pub fn compile_body_with_target(
+  input: &str,
+  callback: impl for<'tcx> FnOnce(TyCtxt<'tcx>, BodyId, &'tcx BodyWithBorrowckFacts<'tcx>, ByteRange)
+    + Send,
+) {
+  // We have to do a hacky thing where we call `parse_ranges` twice.
+  // Once to clean up the input to pass to rustc to start the session.
+  // A second time to get the `ByteRange`s, which *must* happen *within*
+  // the session thread bc filenames are interned.
+  let (input_clean, _) = parse_ranges(input, vec![("`(", ")`")]).unwrap();
+  compile_body_with_range(
+    input_clean,
+    || {
+      let (_, input_ranges) = parse_ranges(input, vec![("`(", ")`")]).unwrap();
+      input_ranges["`("][0]
+    },
+    callback,
+  )
+}
+
 pub fn compile_body(
   input: impl Into<String>,
   callback: impl for<'tcx> FnOnce(TyCtxt<'tcx>, BodyId, &'tcx BodyWithBorrowckFacts<'tcx>)
@@ -54,6 +79,112 @@ pub fn compile(
   test_utils::compile(input, callback)
 }
 
+/// Like [`compile`], but passes `extra_args` to rustc, e.g. `-Z threads=4`.
+pub fn compile_with_args(
+  input: impl Into<String>,
+  extra_args: &[&str],
+  callback: impl for<'tcx> FnOnce(TyCtxt<'tcx>) + Send,
+) {
+  borrowck_facts::enable_mir_simplification();
+  let sysroot = Command::new("rustc")
+    .args(["--print", "sysroot"])
+    .output()
+    .unwrap()
+    .stdout;
+  let sysroot = String::from_utf8(sysroot).unwrap();
+  let args = format!(
+    "rustc {DUMMY_FILE_NAME} --crate-type lib --edition=2021 -Z identify-regions -Z mir-opt-level=0 -Z maximal-hir-to-mir-coverage --allow warnings --sysroot {}",
+    sysroot.trim()
+  );
+  let args = args
+    .split(' ')
+    .chain(extra_args.iter().copied())
+    .map(String::from)
+    .collect::<Vec<_>>();
+
+  let mut callbacks = Callbacks {
+    callback: Some(callback),
+  };
+  rustc_driver::catch_fatal_errors(|| {
+    let mut compiler = rustc_driver::RunCompiler::new(&args, &mut callbacks);
+    compiler.set_file_loader(Some(Box::new(StringLoader(input.into()))));
+    compiler.run()
+  })
+  .unwrap()
+  .unwrap();
+}
+
+struct Callbacks<Cb> {
+  callback: Option<Cb>,
+}
+
+impl<Cb> rustc_driver::Callbacks for Callbacks<Cb>
+where
+  Cb: FnOnce(TyCtxt<'_>),
+{
+  fn config(&mut self, config: &mut rustc_interface::Config) {
+    config.override_queries = Some(borrowck_facts::override_queries);
+  }
+
+  fn after_expansion<'tcx>(
+    &mut self,
+    _compiler: &rustc_interface::interface::Compiler,
+    queries: &'tcx rustc_interface::Queries<'tcx>,
+  ) -> rustc_driver::Compilation {
+    queries.global_ctxt().unwrap().enter(|tcx| {
+      let callback = self.callback.take().unwrap();
+      callback(tcx);
+    });
+    rustc_driver::Compilation::Stop
+  }
+}
+
+/// Returns a target for every local of `body` at every location and argument.
+pub fn all_local_targets<'tcx>(
+  body: &Body<'tcx>,
+) -> Vec<Vec<(Place<'tcx>, LocationOrArg)>> {
+  body
+    .all_locations()
+    .map(LocationOrArg::Location)
+    .chain(body.args_iter().map(LocationOrArg::Arg))
+    .flat_map(|location| {
+      body
+        .local_decls
+        .indices()
+        .map(move |local| vec![(Place::from(local), location)])
+    })
+    .collect()
+}
+
+/// Returns the location of the first `return` terminator in `body`.
+pub fn exit_location(body: &Body) -> Location {
+  body.all_returns().next().expect("body does not return")
+}
+
+/// Returns the names of the variables of `body` that are assigned by a statement in `deps`.
+pub fn assigned_names(
+  body: &Body,
+  deps: impl IntoIterator<Item = LocationOrArg>,
+) -> HashSet<String> {
+  let assigned = deps
+    .into_iter()
+    .filter_map(|dep| match dep {
+      LocationOrArg::Location(location) => body.stmt_at(location).left(),
+      LocationOrArg::Arg(_) => None,
+    })
+    .filter_map(|stmt| match &stmt.kind {
+      StatementKind::Assign(assign) => Some(assign.0.local),
+      _ => None,
+    })
+    .collect::<HashSet<_>>();
+  body
+    .debug_info_name_map()
+    .into_iter()
+    .filter(|(_, local)| assigned.contains(local))
+    .map(|(name, _)| name)
+    .collect()
+}
+
 pub fn bless(
   tcx: TyCtxt,
   path: &Path,
@@ -112,21 +243,18 @@ pub fn test_command_output(
   let inner = move || -> Result<()> {
     info!("Testing {}", path.file_name().unwrap().to_string_lossy());
     let input = String::from_utf8(fs::read(path)?)?;
-
-    // We have to do a hacky thing where we call `parse_ranges` twice.
-    // Once to clean up the input to pass to rustc to start the session.
-    // A second time to get the `ByteRange`s, which *must* happen *within*
-    // the session thread bc filenames are interned.
     let (input_clean, _) = parse_ranges(&input, vec![("`(", ")`")])?;
-    compile_body_with_range(
-      input_clean.clone(),
-      || {
-        let (_, input_ranges) = parse_ranges(&input, vec![("`(", ")`")]).unwrap();
-        input_ranges["`("][0]
-      },
+
+    compile_body_with_target(
+      &input,
       |tcx, body_id, body_with_facts, target: ByteRange| {
         let header = input.lines().next().unwrap();
-        let mut mode = EvalMode::default();
+        // Track every kind of dependency so that commands can read any of them
+        let mut config = FlowConfig {
+          tracked_kinds: TrackedKinds::all(),
+          ..Default::default()
+        };
+        let mode = &mut config.eval_mode;
         if header.starts_with("/*") {
           if header.contains("recurse") {
             mode.context_mode = ContextMode::Recurse;
@@ -137,12 +265,20 @@ pub fn test_command_output(
           if header.contains("conservative") {
             mode.pointer_mode = PointerMode::Conservative;
           }
+          if header.contains("crosscrate") {
+            mode.cross_crate_mode = CrossCrateMode::Recurse;
+          }
+          if header.contains("unsafe") {
+            mode.unsafe_mode = UnsafeMode::Recurse;
+          }
+          if header.contains("summaries") {
+            config.summaries = CallSummaries::std();
+          }
         }
 
-        fluid_set!(EVAL_MODE, &mode);
-
         let target = target.to_span(tcx).unwrap();
-        let results = infoflow::compute_flow(tcx, body_id, body_with_facts);
+        let results =
+          infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
         let spanner = Spanner::new(tcx, body_id, &body_with_facts.body);
 
         let actual = output_fn(results, spanner, target)
@@ -0,0 +1,122 @@
+#![feature(rustc_private)]
+
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::Duration,
+};
+
+use flowistry::{
+  infoflow::{self, DependencyKind, Direction, FlowConfig, TrackedKinds},
+  mir::budget::Budget,
+  test_utils,
+};
+use test_log::test;
+
+#[test]
+fn test_budget() {
+  let input = r#"
+fn main(n: i32, b: bool) -> i32 {
+  let mut x = 0;
+  let mut y = (0, n);
+  let p = &mut y.0;
+  let mut i = 0;
+  while i < n {
+    if b {
+      *p += i;
+    } else {
+      x += 1;
+    }
+    i += 1;
+  }
+  x + y.0 + y.1
+}
+"#;
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let targets = test_utils::all_local_targets(&body_with_facts.body);
+
+    let deps = |budget: Budget| {
+      let config = FlowConfig {
+        budget,
+        tracked_kinds: TrackedKinds::all(),
+        ..Default::default()
+      };
+      let results =
+        infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
+      let degraded = results.analysis.diagnostics().degraded;
+      let deps = [Direction::Backward, Direction::Forward]
+        .into_iter()
+        .flat_map(|direction| {
+          [
+            DependencyKind::All,
+            DependencyKind::Data,
+            DependencyKind::Control,
+            DependencyKind::Thin,
+          ]
+          .into_iter()
+          .map(move |kind| (direction, kind))
+        })
+        .map(|(direction, kind)| {
+          infoflow::compute_dependencies(&results, targets.clone(), direction, kind)
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+      (degraded, deps)
+    };
+
+    let (degraded, expected) = deps(Budget::default());
+    assert!(!degraded);
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancelled = Budget {
+      cancel: Some(cancel.clone()),
+      ..Default::default()
+    };
+    assert_eq!(deps(cancelled.clone()), (false, expected.clone()));
+    cancel.store(true, Ordering::Relaxed);
+
+    let budgets = [
+      Budget {
+        max_iterations: Some(5),
+        ..Default::default()
+      },
+      Budget {
+        max_domain_size: Some(10),
+        ..Default::default()
+      },
+      Budget::with_time_limit(Duration::ZERO),
+      cancelled,
+    ];
+    let demand_deps = |budget: Budget| {
+      let config = FlowConfig {
+        budget,
+        ..Default::default()
+      };
+      infoflow::compute_backward_dependencies(
+        tcx,
+        body_id,
+        body_with_facts,
+        &config,
+        targets.clone(),
+        DependencyKind::All,
+      )
+    };
+    let demand_expected = demand_deps(Budget::default());
+
+    for budget in budgets {
+      let (degraded, actual) = deps(budget.clone());
+      assert!(degraded, "{budget:?}");
+      for (actual, expected) in actual.iter().flatten().zip(expected.iter().flatten()) {
+        assert!(actual.is_superset(expected), "{budget:?}");
+      }
+
+      let actual = demand_deps(budget.clone());
+      assert_ne!(actual, demand_expected, "{budget:?}");
+      for (actual, expected) in actual.iter().zip(&demand_expected) {
+        assert!(actual.is_superset(expected), "{budget:?}");
+      }
+    }
+  });
+}
@@ -0,0 +1,85 @@
+#![feature(rustc_private)]
+
+extern crate rustc_middle;
+
+use flowistry::{
+  infoflow::{
+    self,
+    call_model::{CallModel, CallModels},
+    mutation::{Mutation, MutationStatus, Reason},
+    FlowConfig,
+  },
+  mir::placeinfo::PlaceInfo,
+  test_utils,
+};
+use rustc_middle::{
+  mir::{Location, TerminatorKind},
+  ty::Instance,
+};
+use rustc_utils::{test_utils::Placer, OperandExt};
+use test_log::test;
+
+/// Models `route(x, y)` as only depending on `y`.
+struct RouteModel;
+
+impl<'tcx> CallModel<'tcx> for RouteModel {
+  fn model_call(
+    &self,
+    call: &TerminatorKind<'tcx>,
+    callee: Option<Instance<'tcx>>,
+    place_info: &PlaceInfo<'tcx>,
+    _location: Location,
+  ) -> Option<Vec<Mutation<'tcx>>> {
+    let callee = callee?;
+    if place_info.tcx.def_path_str(callee.def_id()) != "route" {
+      return None;
+    }
+    let TerminatorKind::Call {
+      args, destination, ..
+    } = call
+    else {
+      unreachable!()
+    };
+    Some(vec![Mutation {
+      mutated: *destination,
+      reason: Reason::AssignTarget,
+      inputs: args[1].as_place().into_iter().collect(),
+      status: MutationStatus::Definitely,
+    }])
+  }
+}
+
+#[test]
+fn test_call_model() {
+  let input = r#"
+fn main() {
+  let a = 1;
+  let b = 2;
+  let c = route(a, b);
+}
+
+fn route(x: i32, y: i32) -> i32 {
+  x + y
+}
+"#;
+
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let mut call_models = CallModels::default();
+    call_models.register(RouteModel);
+    let config = FlowConfig {
+      call_models,
+      ..Default::default()
+    };
+
+    let results =
+      infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
+    let body = &body_with_facts.body;
+    let p = Placer::new(tcx, body);
+    let exit = test_utils::exit_location(body);
+    let state = results.shared_state_at(exit);
+    let deps = results.analysis.deps_for(&state, p.local("c").mk());
+    let assigned = test_utils::assigned_names(body, deps.iter().copied());
+    assert!(assigned.contains("b"));
+    assert!(!assigned.contains("a"));
+  });
+}
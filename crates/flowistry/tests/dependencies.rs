@@ -0,0 +1,291 @@
+#![feature(rustc_private)]
+
+extern crate rustc_middle;
+
+use flowistry::{
+  infoflow::{
+    self, DependencyKind, DependencyReason, DependencyStep, Direction, FlowConfig,
+    TrackedKinds, UntrackedKind,
+  },
+  test_utils,
+};
+use rustc_middle::mir::{
+  Local, Place, Rvalue, StatementKind, TerminatorKind, RETURN_PLACE,
+};
+use rustc_utils::{mir::location_or_arg::LocationOrArg, BodyExt};
+use test_log::test;
+
+#[test]
+fn test_dependency_kinds() {
+  let input = r#"
+fn main(a: i32, b: bool) -> i32 {
+  let mut c = a;
+  if b { c = 0; }
+  c
+}
+"#;
+
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let body = &body_with_facts.body;
+    let exit = test_utils::exit_location(body);
+
+    let targets = vec![vec![(
+      Place::from(RETURN_PLACE),
+      LocationOrArg::Location(exit),
+    )]];
+
+    // Only dependencies of all kinds are tracked by default
+    let results = infoflow::compute_flow(tcx, body_id, body_with_facts);
+    let state = results.shared_state_at(exit);
+    assert!(state.data_deps().is_none() && state.thin_deps().is_none());
+    for kind in [
+      DependencyKind::Data,
+      DependencyKind::Control,
+      DependencyKind::Thin,
+    ] {
+      let deps = infoflow::compute_dependencies(
+        &results,
+        targets.clone(),
+        Direction::Backward,
+        kind,
+      );
+      assert_eq!(deps, Err(UntrackedKind(kind)));
+      let deps = results
+        .analysis
+        .deps_for_kind(&state, Place::from(RETURN_PLACE), kind);
+      assert_eq!(deps, Err(UntrackedKind(kind)));
+    }
+
+    let config = FlowConfig {
+      tracked_kinds: TrackedKinds::for_kind(DependencyKind::Control),
+      ..Default::default()
+    };
+    let results =
+      infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
+
+    let deps = |kind| {
+      infoflow::compute_dependencies(&results, targets.clone(), Direction::Backward, kind)
+        .unwrap()
+        .remove(0)
+    };
+    let a = LocationOrArg::Arg(Local::from_usize(1));
+    let b = LocationOrArg::Arg(Local::from_usize(2));
+
+    let data = deps(DependencyKind::Data);
+    assert!(data.contains(a) && !data.contains(b));
+
+    let control = deps(DependencyKind::Control);
+    assert!(!control.contains(a) && control.contains(b));
+
+    let all = deps(DependencyKind::All);
+    assert!(all.contains(a) && all.contains(b));
+    assert_eq!(all.len(), data.len() + control.len());
+  });
+}
+
+#[test]
+fn test_control_only() {
+  let input = r#"
+fn main(a: i32) -> i32 {
+  let mut c = a;
+  if a > 0 { c = 1; }
+  c
+}
+"#;
+
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let body = &body_with_facts.body;
+    let exit = test_utils::exit_location(body);
+    let config = FlowConfig {
+      tracked_kinds: TrackedKinds::all(),
+      ..Default::default()
+    };
+    let results =
+      infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
+    let targets = vec![vec![(
+      Place::from(RETURN_PLACE),
+      LocationOrArg::Location(exit),
+    )]];
+    let deps = |kind| {
+      infoflow::compute_dependencies(&results, targets.clone(), Direction::Backward, kind)
+        .unwrap()
+        .remove(0)
+    };
+    let a = LocationOrArg::Arg(Local::from_usize(1));
+    let switch = body
+      .all_locations()
+      .find(|location| {
+        body.stmt_at(*location).right().is_some_and(|terminator| {
+          matches!(terminator.kind, TerminatorKind::SwitchInt { .. })
+        })
+      })
+      .map(LocationOrArg::Location)
+      .unwrap();
+
+    // a is both a data and a control dependency of the return value, so it is only reported as data
+    let data = deps(DependencyKind::Data);
+    let control = deps(DependencyKind::Control);
+    assert!(data.contains(a) && !control.contains(a));
+    assert!(control.contains(switch) && !data.contains(switch));
+
+    let demand_control = infoflow::compute_backward_dependencies(
+      tcx,
+      body_id,
+      body_with_facts,
+      &FlowConfig::default(),
+      targets.clone(),
+      DependencyKind::Control,
+    )
+    .remove(0);
+    assert_eq!(demand_control, control);
+  });
+}
+
+#[test]
+fn test_chop() {
+  let input = r#"
+fn main(a: i32, b: i32) -> i32 {
+  let x = a + 1;
+  let y = b + 2;
+  let z = x * y;
+  let w = z - y;
+  w
+}
+"#;
+
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let results = infoflow::compute_flow(tcx, body_id, body_with_facts);
+    let body = &body_with_facts.body;
+    let exit = test_utils::exit_location(body);
+    let a = Local::from_usize(1);
+    let sources = vec![(Place::from(a), LocationOrArg::Arg(a))];
+    let sinks = vec![(Place::from(RETURN_PLACE), LocationOrArg::Location(exit))];
+
+    let chop = infoflow::compute_chop(&results, sources, sinks);
+    let assigned = test_utils::assigned_names(body, chop.iter().copied());
+    for name in ["x", "z", "w"] {
+      assert!(assigned.contains(name), "{name} is not in the chop");
+    }
+    assert!(!assigned.contains("y"));
+    assert!(chop.contains(LocationOrArg::Arg(a)));
+    assert!(!chop.contains(LocationOrArg::Arg(Local::from_usize(2))));
+  });
+}
+
+#[test]
+fn test_explain_dependency() {
+  let input = r#"
+fn main(a: i32, b: bool) -> i32 {
+  let p = &a;
+  let x = *p + 1;
+  let mut y = 0;
+  if b { y = x; }
+  let z = id(y);
+  z
+}
+
+fn id(v: i32) -> i32 { v }
+"#;
+
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let results = infoflow::compute_flow(tcx, body_id, body_with_facts);
+    let body = &body_with_facts.body;
+    let exit = test_utils::exit_location(body);
+    let target = (Place::from(RETURN_PLACE), LocationOrArg::Location(exit));
+    let explain = |dep| infoflow::explain_dependency(&results, target, dep);
+    let reasons =
+      |steps: &[DependencyStep]| steps.iter().map(|step| step.reason).collect::<Vec<_>>();
+
+    // a flows into x through the pointer p, and then into y, z and the return value
+    let a = LocationOrArg::Arg(Local::from_usize(1));
+    let steps = explain(a).unwrap();
+    assert_eq!(steps[0].location, a);
+    assert_eq!(steps.last().unwrap().location, target.1);
+    assert!(reasons(&steps).contains(&Some(DependencyReason::Call)));
+    assert!(!reasons(&steps).contains(&Some(DependencyReason::Control)));
+
+    // b only determines whether y is assigned
+    let b = LocationOrArg::Arg(Local::from_usize(2));
+    let steps = explain(b).unwrap();
+    assert!(reasons(&steps).contains(&Some(DependencyReason::Control)));
+
+    // p = &a is needed to read *p
+    let p_loc = body
+      .all_locations()
+      .find(|location| {
+        matches!(
+          body.stmt_at(*location).left().map(|stmt| &stmt.kind),
+          Some(StatementKind::Assign(assign)) if matches!(assign.1, Rvalue::Ref(..))
+        )
+      })
+      .unwrap();
+    let steps = explain(LocationOrArg::Location(p_loc)).unwrap();
+    assert_eq!(steps[1].reason, Some(DependencyReason::Provenance));
+
+    // Locations outside of the slice have no explanation
+    let deps = infoflow::compute_dependencies(
+      &results,
+      vec![vec![target]],
+      Direction::Backward,
+      DependencyKind::All,
+    )
+    .unwrap()
+    .remove(0);
+    let outside = body
+      .all_locations()
+      .map(LocationOrArg::Location)
+      .find(|location| !deps.contains(*location))
+      .unwrap();
+    assert!(explain(outside).is_none());
+  });
+}
+
+#[test]
+fn test_backward_dependencies() {
+  for dir in ["backward_slice", "thin_slice"] {
+    test_utils::run_tests(dir, |path, _| {
+      let input = std::fs::read_to_string(path).unwrap();
+      test_utils::compile_body_with_target(
+        &input,
+        move |tcx, body_id, body_with_facts, _| {
+          let results = infoflow::compute_flow_with_config(
+            tcx,
+            body_id,
+            body_with_facts,
+            &FlowConfig {
+              tracked_kinds: TrackedKinds::all(),
+              ..Default::default()
+            },
+          );
+          let targets = test_utils::all_local_targets(&body_with_facts.body);
+
+          for kind in [
+            DependencyKind::All,
+            DependencyKind::Data,
+            DependencyKind::Control,
+            DependencyKind::Thin,
+          ] {
+            let expected = infoflow::compute_dependencies(
+              &results,
+              targets.clone(),
+              Direction::Backward,
+              kind,
+            )
+            .unwrap();
+            let actual = infoflow::compute_backward_dependencies(
+              tcx,
+              body_id,
+              body_with_facts,
+              &FlowConfig::default(),
+              targets.clone(),
+              kind,
+            );
+            for ((target, expected), actual) in targets.iter().zip(expected).zip(actual) {
+              assert_eq!(actual, expected, "{kind:?} dependencies of {target:?}");
+            }
+          }
+        },
+      );
+    });
+  }
+}
@@ -0,0 +1,68 @@
+#![feature(rustc_private)]
+
+use flowistry::{
+  extensions::{ContextMode, EvalMode, UnsafeMode},
+  infoflow::{self, FlowConfig},
+  test_utils,
+};
+use test_log::test;
+
+#[test]
+fn test_unsafe_callees() {
+  let input = r#"
+fn main() {
+  let mut x = 0;
+  write(&mut x, 1);
+}
+
+fn write(p: *mut i32, v: i32) {
+  unsafe { *p = v; }
+}
+"#;
+
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let config = FlowConfig::new(EvalMode {
+      context_mode: ContextMode::Recurse,
+      unsafe_mode: UnsafeMode::Recurse,
+      ..Default::default()
+    });
+
+    let results =
+      infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
+    let callees = results
+      .analysis
+      .diagnostics()
+      .unsafe_callees
+      .into_iter()
+      .map(|def_id| tcx.def_path_str(def_id))
+      .collect::<Vec<_>>();
+    assert_eq!(callees, ["write"]);
+  });
+}
+
+#[test]
+fn test_reached_library() {
+  let input = r#"
+fn main() {
+  let mut v = Vec::new();
+  v.push(1);
+}
+"#;
+
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let recurse = FlowConfig::new(EvalMode {
+      context_mode: ContextMode::Recurse,
+      ..Default::default()
+    });
+    let sig_only = FlowConfig::default();
+
+    // Analyses with different configurations can be computed side by side
+    let recurse_results =
+      infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &recurse);
+    let sig_only_results =
+      infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &sig_only);
+
+    assert!(recurse_results.analysis.diagnostics().reached_library);
+    assert!(!sig_only_results.analysis.diagnostics().reached_library);
+  });
+}
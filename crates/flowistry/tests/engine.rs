@@ -0,0 +1,108 @@
+#![feature(rustc_private)]
+
+extern crate rustc_middle;
+
+use std::collections::HashSet;
+
+use flowistry::{
+  extensions::{ContextMode, EvalMode},
+  infoflow::{self, FlowConfig, FlowResults},
+  mir::engine::EngineMode,
+  test_utils,
+};
+use rustc_middle::mir::{Location, Place};
+use rustc_utils::{mir::location_or_arg::LocationOrArg, BodyExt};
+use test_log::test;
+
+fn all_deps<'tcx>(
+  results: &FlowResults<'tcx>,
+) -> HashSet<(Location, Place<'tcx>, Vec<LocationOrArg>)> {
+  results
+    .analysis
+    .body
+    .all_locations()
+    .flat_map(|location| {
+      results
+        .shared_state_at(location)
+        .rows()
+        .map(|(place, deps)| (location, *place, deps.iter().copied().collect()))
+        .collect::<Vec<_>>()
+    })
+    .collect()
+}
+
+#[test]
+fn test_engine_modes() {
+  test_utils::run_tests("backward_slice", |path, _| {
+    let input = std::fs::read_to_string(path).unwrap();
+    let context_mode = if input.lines().next().unwrap().contains("recurse") {
+      ContextMode::Recurse
+    } else {
+      ContextMode::SigOnly
+    };
+    // The body is the one that contains the target, see test_utils::test_command_output
+    test_utils::compile_body_with_target(
+      &input,
+      move |tcx, body_id, body_with_facts, _| {
+        let deps = |engine_mode| {
+          let config = FlowConfig {
+            eval_mode: EvalMode {
+              context_mode,
+              ..Default::default()
+            },
+            engine_mode,
+            ..Default::default()
+          };
+          let results =
+            infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
+          all_deps(&results)
+        };
+
+        let expected = deps(EngineMode::AllLocations);
+        for mode in [EngineMode::Blocks, EngineMode::RowDeltas] {
+          assert_eq!(deps(mode), expected, "{mode:?}");
+        }
+      },
+    );
+  });
+}
+
+#[test]
+fn test_state_accessors() {
+  let input = r#"
+fn main(n: i32, b: bool) -> i32 {
+  let mut x = 0;
+  let mut i = 0;
+  while i < n {
+    if b { x += i; }
+    i += 1;
+  }
+  x
+}
+"#;
+
+  test_utils::compile_body(input, |tcx, body_id, body_with_facts| {
+    let body = &body_with_facts.body;
+    let states = |engine_mode| {
+      let config = FlowConfig {
+        engine_mode,
+        ..Default::default()
+      };
+      let results =
+        infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config);
+      body
+        .all_locations()
+        .map(|location| {
+          let at = results.shared_state_at(location);
+          let before = results.state_before(body, location);
+          ((*at).clone(), (*before).clone())
+        })
+        .collect::<Vec<_>>()
+    };
+
+    let expected = states(EngineMode::AllLocations);
+    for mode in [EngineMode::Blocks, EngineMode::RowDeltas] {
+      assert!(states(mode) == expected, "{mode:?}");
+    }
+  });
+}
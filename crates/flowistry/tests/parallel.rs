@@ -0,0 +1,149 @@
+//! Tests of the analysis under rustc's parallel front-end.
+//!
+//! rustc can only enable its thread-safe mode once per process, so these tests have their own
+//! test binary, and every compilation in it is started with the same number of threads.
+
+#![feature(rustc_private)]
+
+extern crate rustc_data_structures;
+extern crate rustc_hir;
+extern crate rustc_middle;
+
+use flowistry::{
+  extensions::{ContextMode, EvalMode},
+  infoflow::{self, FlowConfig, FlowResults},
+  test_utils,
+};
+use rustc_data_structures::sync::is_dyn_thread_safe;
+use rustc_hir::{def::DefKind, def_id::LocalDefId};
+use rustc_middle::ty::TyCtxt;
+use rustc_utils::{mir::borrowck_facts::get_body_with_borrowck_facts, BodyExt};
+
+const INPUT: &str = r#"
+const LIMIT: u32 = 3;
+
+fn add(x: &mut i32, y: i32) {
+  *x += y;
+}
+
+fn even(n: u32, acc: &mut i32) {
+  if n > 0 {
+    add(acc, 1);
+    odd(n - 1, acc);
+  }
+}
+
+fn odd(n: u32, acc: &mut i32) {
+  if n > 0 {
+    even(n - 1, acc);
+  }
+}
+
+fn adder(y: i32) -> impl Fn(&mut i32) {
+  move |x| add(x, y)
+}
+
+fn main() {
+  let mut a = 0;
+  let buf = [0; LIMIT as usize];
+  add(&mut a, buf.len() as i32);
+  even(LIMIT, &mut a);
+  let _f = adder(2);
+  let _g = |n| odd(n, &mut a);
+}
+"#;
+
+const THREADS: &[&str] = &["-Z", "threads=4"];
+
+/// The dependencies of every place at every location, in a form that can be compared
+/// across compilations.
+fn all_deps(results: &FlowResults<'_>) -> Vec<String> {
+  let mut deps = results
+    .analysis
+    .body
+    .all_locations()
+    .flat_map(|location| {
+      results
+        .shared_state_at(location)
+        .rows()
+        .map(|(place, deps)| {
+          let deps = deps.iter().collect::<Vec<_>>();
+          format!("{location:?} {place:?} {deps:?}")
+        })
+        .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>();
+  deps.sort();
+  deps
+}
+
+fn config() -> FlowConfig {
+  FlowConfig::new(EvalMode {
+    context_mode: ContextMode::Recurse,
+    ..Default::default()
+  })
+}
+
+fn named(tcx: TyCtxt<'_>, def_id: LocalDefId) -> String {
+  tcx.def_path_str(def_id)
+}
+
+#[test]
+fn test_parallel_crate_flow() {
+  let mut parallel = Vec::new();
+  test_utils::compile_with_args(INPUT, THREADS, |tcx| {
+    assert!(is_dyn_thread_safe());
+    parallel =
+      infoflow::compute_crate_flow(tcx, &config(), |_, results| all_deps(results))
+        .into_iter()
+        .map(|(def_id, deps)| (named(tcx, def_id), deps))
+        .collect();
+  });
+
+  let mut sequential = Vec::new();
+  test_utils::compile_with_args(INPUT, THREADS, |tcx| {
+    // Borrow-checking steals the MIR that the unsafety check of a callee reads
+    for def_id in tcx.hir().body_owners() {
+      if matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn) {
+        tcx.ensure().mir_unsafety_check_result(def_id);
+      }
+    }
+
+    sequential = tcx
+      .hir()
+      .body_owners()
+      .map(|def_id| {
+        let body_id = tcx.hir().body_owned_by(def_id);
+        let body_with_facts = get_body_with_borrowck_facts(tcx, def_id);
+        let results =
+          infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, &config());
+        (named(tcx, def_id), all_deps(&results))
+      })
+      .collect();
+  });
+
+  let names = |deps: &[(String, Vec<String>)]| {
+    deps
+      .iter()
+      .map(|(name, _)| name.clone())
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(names(&parallel), names(&sequential));
+  for ((name, parallel), (_, sequential)) in parallel.iter().zip(&sequential) {
+    assert_eq!(parallel, sequential, "{name}");
+  }
+}
+
+#[test]
+#[should_panic(expected = "add was already borrow-checked")]
+fn test_crate_flow_after_borrowck() {
+  test_utils::compile_with_args(INPUT, THREADS, |tcx| {
+    let add = tcx
+      .hir()
+      .body_owners()
+      .find(|def_id| named(tcx, *def_id) == "add")
+      .unwrap();
+    tcx.ensure().mir_borrowck(add);
+    infoflow::compute_crate_flow(tcx, &config(), |_, _| ());
+  });
+}
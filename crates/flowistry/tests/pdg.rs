@@ -0,0 +1,472 @@
+#![feature(rustc_private)]
+
+extern crate rustc_middle;
+
+use std::collections::HashSet;
+
+use flowistry::{
+  extensions::ContextMode,
+  pdg::{compute_pdg, DepGraph, DepNodeKind, PdgParams},
+  test_utils,
+};
+use flowistry_pdg::{export, query::GraphQuery, CallStringTable};
+use petgraph::algo::has_path_connecting;
+use rustc_middle::ty::TyCtxt;
+use rustc_utils::source_map::find_bodies::find_bodies;
+use test_log::test;
+
+fn pdg(
+  input: &str,
+  root: &str,
+  configure: impl for<'tcx> FnOnce(PdgParams<'tcx>) -> PdgParams<'tcx> + Send,
+  tests: impl for<'tcx> FnOnce(TyCtxt<'tcx>, DepGraph<'tcx>) + Send,
+) {
+  test_utils::compile(input, move |tcx| {
+    let def_id = find_bodies(tcx)
+      .into_iter()
+      .map(|(_, body_id)| tcx.hir().body_owner_def_id(body_id))
+      .find(|def_id| tcx.def_path_str(*def_id) == root)
+      .unwrap();
+    let params = configure(PdgParams::new(tcx, def_id));
+    let graph = compute_pdg(params);
+    log::debug!("{graph:#?}");
+    tests(tcx, graph)
+  })
+}
+
+fn node_name(graph: &DepGraph, node: petgraph::graph::NodeIndex) -> String {
+  let node = &graph.graph[node];
+  match node.place_pretty() {
+    Some(s) => s.to_owned(),
+    None => format!("{:?}", node.place),
+  }
+}
+
+/// Returns true if any node for the place named `src` reaches any node for the place named `dst`.
+fn connects(graph: &DepGraph, src: &str, dst: &str) -> bool {
+  let nodes = |name: &str| {
+    graph
+      .graph
+      .node_indices()
+      .filter(|n| node_name(graph, *n) == name)
+      .collect::<Vec<_>>()
+  };
+  let (srcs, dsts) = (nodes(src), nodes(dst));
+  assert!(!srcs.is_empty(), "no node named {src}");
+  assert!(!dsts.is_empty(), "no node named {dst}");
+  srcs.iter().any(|s| {
+    dsts
+      .iter()
+      .any(|d| s != d && has_path_connecting(&graph.graph, *s, *d, None))
+  })
+}
+
+#[test]
+fn test_pdg_data_flow() {
+  let input = r#"
+fn main() {
+  let a = 1;
+  let b = a + 1;
+  let c = 2;
+  let d = b + c;
+  let e = c;
+}
+"#;
+  pdg(
+    input,
+    "main",
+    |params| params,
+    |_, graph| {
+      assert!(connects(&graph, "a", "d"));
+      assert!(connects(&graph, "c", "d"));
+      assert!(!connects(&graph, "a", "e"));
+      assert!(!connects(&graph, "d", "a"));
+    },
+  );
+}
+
+#[test]
+fn test_pdg_strong_update() {
+  let input = r#"
+fn main() {
+  let x = 1;
+  let mut a = x;
+  a = 2;
+  let c = a;
+}
+"#;
+  pdg(
+    input,
+    "main",
+    |params| params,
+    |_, graph| {
+      assert!(connects(&graph, "x", "a"));
+      assert!(!connects(&graph, "x", "c"));
+    },
+  );
+}
+
+#[test]
+fn test_pdg_control_flow() {
+  let input = r#"
+fn main() {
+  let x = std::env::args().count();
+  let mut y = 0;
+  if x > 0 {
+    y = 1;
+  }
+  let z = y;
+}
+"#;
+  pdg(
+    input,
+    "main",
+    |params| params,
+    |_, graph| {
+      assert!(connects(&graph, "x", "z"));
+      assert!(graph
+        .graph
+        .edge_weights()
+        .any(|edge| edge.kind == flowistry::pdg::DepEdgeKind::Control));
+    },
+  );
+}
+
+#[test]
+fn test_pdg_pointers() {
+  let input = r#"
+fn main() {
+  let mut x = 1;
+  let y = &mut x;
+  let z = 2;
+  *y = z;
+  let w = x;
+}
+"#;
+  pdg(
+    input,
+    "main",
+    |params| params,
+    |_, graph| {
+      assert!(connects(&graph, "z", "w"));
+    },
+  );
+}
+
+#[test]
+fn test_pdg_arguments() {
+  let input = r#"
+fn f(x: i32, y: i32, z: &mut i32) -> i32 {
+  *z = y;
+  x
+}
+"#;
+  pdg(
+    input,
+    "f",
+    |params| params,
+    |_, graph| {
+      assert!(connects(&graph, "x", "RETURN"));
+      assert!(!connects(&graph, "y", "RETURN"));
+      assert!(connects(&graph, "y", "*z"));
+    },
+  );
+}
+
+#[test]
+fn test_pdg_inline_call_sites() {
+  let input = r#"
+fn id(x: i32) -> i32 {
+  x
+}
+
+fn main() {
+  let a = 1;
+  let b = 2;
+  let c = id(a);
+  let d = id(b);
+}
+"#;
+  pdg(
+    input,
+    "main",
+    |params| params.with_context_mode(ContextMode::Recurse),
+    |_, graph| {
+      assert!(connects(&graph, "a", "c"));
+      assert!(connects(&graph, "b", "d"));
+      assert!(!connects(&graph, "a", "d"));
+      assert!(!connects(&graph, "b", "c"));
+
+      let callee_call_strings = graph
+        .graph
+        .node_indices()
+        .filter(|n| node_name(&graph, *n) == "x")
+        .map(|n| {
+          let at = graph.graph[n].at;
+          assert_eq!(at.len(), 2);
+          at.caller()
+        })
+        .collect::<HashSet<_>>();
+      assert_eq!(callee_call_strings.len(), 2);
+    },
+  );
+}
+
+#[test]
+fn test_pdg_inline_precision() {
+  let input = r#"
+fn f(x: i32, y: i32, p: &mut i32, q: &mut i32) -> i32 {
+  *p = y;
+  x
+}
+
+fn main() {
+  let a = 1;
+  let b = 2;
+  let mut m = 0;
+  let mut n = 0;
+  let c = f(a, b, &mut m, &mut n);
+  let d = m;
+  let e = n;
+}
+"#;
+  pdg(
+    input,
+    "main",
+    |params| params.with_context_mode(ContextMode::Recurse),
+    |_, graph| {
+      assert!(connects(&graph, "a", "c"));
+      assert!(!connects(&graph, "b", "c"));
+      assert!(connects(&graph, "b", "d"));
+      assert!(!connects(&graph, "a", "d"));
+      assert!(!connects(&graph, "b", "e"));
+    },
+  );
+
+  pdg(
+    input,
+    "main",
+    |params| params.with_context_mode(ContextMode::SigOnly),
+    |_, graph| {
+      assert!(connects(&graph, "b", "c"));
+      assert!(connects(&graph, "b", "e"));
+    },
+  );
+}
+
+#[test]
+fn test_pdg_portable() {
+  let input = r#"
+fn f(x: i32, y: &mut i32) -> i32 {
+  *y = x;
+  let z = g(x);
+  z
+}
+
+fn g(x: i32) -> i32 {
+  x
+}
+"#;
+  pdg(
+    input,
+    "f",
+    |params| params.with_context_mode(ContextMode::Recurse),
+    |tcx, graph| {
+      let portable = graph.to_portable(tcx);
+      assert_eq!(portable.nodes.len(), graph.graph.node_count());
+      assert_eq!(portable.edges.len(), graph.graph.edge_count());
+
+      let kinds = portable
+        .nodes
+        .iter()
+        .map(|node| node.kind)
+        .collect::<HashSet<_>>();
+      assert!(kinds.contains(&DepNodeKind::Argument(0)));
+      assert!(kinds.contains(&DepNodeKind::Argument(1)));
+      assert!(kinds.contains(&DepNodeKind::Return));
+      assert!(kinds.contains(&DepNodeKind::CallSite));
+      assert!(portable.nodes.iter().any(|node| node.at.len() == 2));
+
+      for node in graph.graph.node_indices() {
+        let place = &portable.nodes[node.index()].place;
+        assert_eq!(place.to_string(), format!("{:?}", graph.graph[node].place));
+      }
+
+      let write = portable
+        .nodes
+        .iter()
+        .find(|node| {
+          node.place_pretty.as_deref() == Some("*y") && node.at.leaf().location.is_real()
+        })
+        .unwrap();
+      assert_eq!(write.span.start.line, 3);
+      assert_eq!(write.span.start.column, 2);
+
+      let serialized = serde_json::to_string(&portable).unwrap();
+      let deserialized: flowistry_pdg::graph::DepGraph =
+        serde_json::from_str(&serialized).unwrap();
+      assert_eq!(portable, deserialized);
+    },
+  );
+}
+
+#[test]
+fn test_pdg_stable_ids() {
+  let input = r#"
+fn f(x: i32) -> i32 {
+  g(x)
+}
+
+fn g(x: i32) -> i32 {
+  x + 1
+}
+"#;
+  let stable_ids = || {
+    let (send, recv) = std::sync::mpsc::channel();
+    pdg(
+      input,
+      "f",
+      |params| params.with_context_mode(ContextMode::Recurse),
+      move |tcx, graph| {
+        let table = graph.to_portable(tcx).call_strings().unwrap();
+        for (id, call_string) in table.iter() {
+          assert_eq!(call_string.stable_id(), id);
+        }
+
+        let serialized = serde_json::to_string(&table).unwrap();
+        let deserialized: CallStringTable = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(table, deserialized);
+
+        let mut ids = table.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        ids.sort();
+        send.send(ids).unwrap();
+      },
+    );
+    recv.recv().unwrap()
+  };
+
+  let ids = stable_ids();
+  assert!(!ids.is_empty());
+  assert_eq!(ids, stable_ids());
+}
+
+#[test]
+fn test_pdg_query() {
+  let input = r#"
+fn id(x: i32) -> i32 {
+  x
+}
+
+fn main() {
+  let a = 1;
+  let b = 2;
+  let c = id(a);
+  let d = id(b);
+}
+"#;
+  pdg(
+    input,
+    "main",
+    |params| params.with_context_mode(ContextMode::Recurse),
+    |tcx, graph| {
+      let id = find_bodies(tcx)
+        .into_iter()
+        .map(|(_, body_id)| tcx.hir().body_owner_def_id(body_id))
+        .find(|def_id| tcx.def_path_str(*def_id) == "id")
+        .unwrap();
+
+      let portable = graph.to_portable(tcx);
+      let query = GraphQuery::new(&portable).unwrap();
+      let named = |name: &'static str| {
+        query
+          .nodes_where(move |node| node.place_pretty.as_deref() == Some(name))
+          .collect::<Vec<_>>()
+      };
+      let (a, b, c, d) = (named("a"), named("b"), named("c"), named("d"));
+
+      assert!(query.connects(a.clone(), c.clone()));
+      assert!(!query.connects(a.clone(), d.clone()));
+      assert!(query.backward(d.clone()).iter().all(|n| !a.contains(n)));
+
+      let path = query.shortest_path(a.clone(), c.clone()).unwrap();
+      assert!(a.contains(path.first().unwrap()));
+      assert!(c.contains(path.last().unwrap()));
+      for pair in path.windows(2) {
+        assert!(portable.outgoing(pair[0]).any(|(next, _)| next == pair[1]));
+      }
+      assert!(query.shortest_path(b.clone(), c.clone()).is_none());
+
+      let in_id = query.nodes_in_function(id).collect::<Vec<_>>();
+      let callers = query
+        .call_strings(in_id.iter().copied())
+        .into_iter()
+        .map(|at| at.caller())
+        .collect::<HashSet<_>>();
+      assert_eq!(callers.len(), 2);
+
+      let chop = query.chop(a.clone(), c.clone());
+      let chop_callers = query
+        .call_strings(chop.iter().copied().filter(|n| in_id.contains(n)))
+        .into_iter()
+        .map(|at| at.caller())
+        .collect::<HashSet<_>>();
+      assert_eq!(chop_callers.len(), 1);
+      let call_site = chop_callers.into_iter().next().unwrap();
+      let under_call_site = query.nodes_with_prefix(call_site).collect::<HashSet<_>>();
+      assert!(under_call_site.iter().any(|n| in_id.contains(n)));
+      assert!(under_call_site
+        .iter()
+        .all(|n| in_id.contains(n) || portable[*n].at == call_site));
+      let reaches_d = query.backward(d);
+      assert!(under_call_site.iter().all(|n| !reaches_d.contains(n)));
+
+      // Nodes that are not in the graph are ignored instead of panicking in the query
+      let missing = portable.nodes.len();
+      assert_eq!(query.forward([missing]), HashSet::new());
+      assert_eq!(query.backward([missing]), HashSet::new());
+      assert!(!query.connects([missing], c.clone()));
+      assert!(query.shortest_path([missing], c.clone()).is_none());
+      assert!(query.shortest_path(a.clone(), [missing]).is_none());
+      let path = query
+        .shortest_path(a.iter().copied().chain([missing]), c.clone())
+        .unwrap();
+      assert!(a.contains(path.first().unwrap()));
+      assert!(query.call_strings([missing]).is_empty());
+
+      // A malformed dump is rejected instead of panicking in the query
+      let mut malformed = portable.clone();
+      let edge = malformed.edges[0].2;
+      malformed.edges.push((0, malformed.nodes.len(), edge));
+      assert!(GraphQuery::new(&malformed).is_err());
+    },
+  );
+}
+
+#[test]
+fn test_pdg_export() {
+  let input = r#"
+fn main() {
+  let a = 1;
+  let b = a + 1;
+}
+"#;
+  pdg(
+    input,
+    "main",
+    |params| params,
+    |tcx, graph| {
+      let portable = graph.to_portable(tcx);
+      let label = |index, _: &_| format!("<node {index}>");
+
+      let dot = export::to_dot(&portable, label);
+      assert!(dot.starts_with("digraph {"));
+      assert_eq!(dot.matches(" -> ").count(), portable.edges.len());
+      assert!(dot.contains("label=\"<node 0>\""));
+
+      let graphml = export::to_graphml(&portable, label);
+      assert_eq!(graphml.matches("<node id=").count(), portable.nodes.len());
+      assert_eq!(graphml.matches("<edge id=").count(), portable.edges.len());
+      assert!(graphml.contains("&lt;node 0&gt;"));
+    },
+  );
+}
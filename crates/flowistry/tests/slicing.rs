@@ -3,14 +3,14 @@
 extern crate rustc_span;
 
 use flowistry::{
-  infoflow::{self, Direction},
+  infoflow::{self, DependencyKind, Direction},
   test_utils,
 };
 use rustc_span::Span;
 use rustc_utils::SpanExt;
 use test_log::test;
 
-fn slice(dir: &str, direction: Direction) {
+fn slice(dir: &str, direction: Direction, kind: DependencyKind) {
   test_utils::run_tests(dir, |path, expected| {
     test_utils::test_command_output(path, expected, |results, spanner, target| {
       let places = spanner.span_to_places(target);
@@ -27,7 +27,8 @@ fn slice(dir: &str, direction: Direction) {
       log::debug!("targets={targets:#?}");
 
       let deps =
-        infoflow::compute_dependency_spans(&results, targets, direction, &spanner);
+        infoflow::compute_dependency_spans(&results, targets, direction, &spanner, kind)
+          .unwrap();
 
       Span::merge_overlaps(deps.into_iter().flatten().collect())
     });
@@ -36,15 +37,20 @@ fn slice(dir: &str, direction: Direction) {
 
 #[test]
 fn test_backward_slice() {
-  slice("backward_slice", Direction::Backward);
+  slice("backward_slice", Direction::Backward, DependencyKind::All);
 }
 
 #[test]
 fn test_forward_slice() {
-  slice("forward_slice", Direction::Forward);
+  slice("forward_slice", Direction::Forward, DependencyKind::All);
 }
 
 #[test]
 fn test_extensions() {
-  slice("extensions", Direction::Backward);
+  slice("extensions", Direction::Backward, DependencyKind::All);
+}
+
+#[test]
+fn test_thin_slice() {
+  slice("thin_slice", Direction::Backward, DependencyKind::Thin);
 }
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use flowistry::infoflow::{self, FlowConfig};
+use rustc_hir::BodyId;
+use rustc_middle::ty::TyCtxt;
+use rustc_utils::{
+  mir::borrowck_facts::get_body_with_borrowck_facts,
+  source_map::{
+    range::{CharRange, ToSpan},
+    spanner::Spanner,
+  },
+};
+use serde::Serialize;
+
+use crate::targets::{span_targets, to_ranges};
+
+#[derive(Debug, Serialize)]
+pub struct ChopOutput {
+  /// The places selected as sources of the chop
+  pub sources: Vec<CharRange>,
+  /// The places selected as sinks of the chop
+  pub sinks: Vec<CharRange>,
+  /// The code on some flow from a source to a sink
+  pub slice: Vec<CharRange>,
+}
+
+/// Computes the chop between the places at `source` and the places at `sink`.
+pub fn chop(
+  tcx: TyCtxt,
+  body_id: BodyId,
+  config: &FlowConfig,
+  source: CharRange,
+  sink: CharRange,
+) -> Result<ChopOutput> {
+  let def_id = tcx.hir().body_owner_def_id(body_id);
+  let body_with_facts = get_body_with_borrowck_facts(tcx, def_id);
+  let body = &body_with_facts.body;
+  let results =
+    &infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, config);
+
+  let source_map = tcx.sess.source_map();
+  let spanner = Spanner::new(tcx, body_id, body);
+
+  let (source_spans, sources) = span_targets(&spanner, source.to_span(tcx)?);
+  let (sink_spans, sinks) = span_targets(&spanner, sink.to_span(tcx)?);
+
+  let slice = infoflow::compute_chop_spans(results, sources, sinks, &spanner);
+
+  Ok(ChopOutput {
+    sources: to_ranges(source_spans, source_map),
+    sinks: to_ranges(sink_spans, source_map),
+    slice: to_ranges(slice, source_map),
+  })
+}
@@ -25,7 +25,7 @@ pub fn compute_adjacency_matrix(
   let mut adj_mtx = IndexMatrix::new(location_domain);
 
   ModularMutationVisitor::new(&results.analysis.aliases, |_, inputs, location, _| {
-    let state = results.state_at(location);
+    let state = results.shared_state_at(location);
     for (place, _) in inputs {
       adj_mtx.union_into_row(location, &state.row_set(*place));
     }
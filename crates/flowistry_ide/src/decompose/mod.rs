@@ -27,11 +27,16 @@ pub struct DecomposeOutput {
   chunks: Vec<(f64, Vec<Vec<Range>>)>,
 }
 
-pub fn decompose(tcx: TyCtxt, body_id: BodyId) -> Result<DecomposeOutput> {
+pub fn decompose(
+  tcx: TyCtxt,
+  body_id: BodyId,
+  config: &infoflow::FlowConfig,
+) -> Result<DecomposeOutput> {
   let def_id = tcx.hir().body_owner_def_id(body_id);
   let body_with_facts = get_body_with_borrowck_facts(tcx, def_id);
   let body = &body_with_facts.body;
-  let results = &infoflow::compute_flow(tcx, body_id, body_with_facts);
+  let results =
+    &infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, config);
 
   let source_map = tcx.sess.source_map();
   let spanner = source_map::Spanner::new(tcx, body_id, body);
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use flowistry::infoflow::{self, DependencyReason, FlowConfig};
+use rustc_hir::BodyId;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+use rustc_utils::{
+  mir::{borrowck_facts::get_body_with_borrowck_facts, location_or_arg::LocationOrArg},
+  source_map::{
+    range::{CharRange, ToSpan},
+    spanner::{EnclosingHirSpans, Spanner},
+  },
+  BodyExt, PlaceExt, SpanExt,
+};
+use serde::Serialize;
+
+use crate::targets::{span_targets, to_ranges};
+
+#[derive(Debug, Serialize)]
+pub struct ExplainStep {
+  /// The place that is written (or for branches, read) at this step
+  pub place: String,
+  /// The code of this step
+  pub ranges: Vec<CharRange>,
+  /// How this step depends on the previous step, if any
+  pub reason: Option<DependencyReason>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExplainOutput {
+  /// The steps from the dependency to the target
+  pub steps: Vec<ExplainStep>,
+}
+
+/// Explains why the code at `dep` is in the backward slice of the place at `target`.
+pub fn explain(
+  tcx: TyCtxt,
+  body_id: BodyId,
+  config: &FlowConfig,
+  target: CharRange,
+  dep: CharRange,
+) -> Result<ExplainOutput> {
+  let def_id = tcx.hir().body_owner_def_id(body_id);
+  let body_with_facts = get_body_with_borrowck_facts(tcx, def_id);
+  let body = &body_with_facts.body;
+  let results =
+    &infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, config);
+
+  let source_map = tcx.sess.source_map();
+  let spanner = Spanner::new(tcx, body_id, body);
+  let location_spans = |location: LocationOrArg| {
+    spanner.location_to_spans(location, body, EnclosingHirSpans::OuterOnly)
+  };
+
+  let (_, targets) = span_targets(&spanner, target.to_span(tcx)?);
+
+  // The dependency is any location whose code contains the selected position
+  let dep_span = dep.to_span(tcx)?;
+  let deps = body
+    .args_iter()
+    .map(LocationOrArg::Arg)
+    .chain(body.all_locations().map(LocationOrArg::Location))
+    .filter(|location| {
+      location_spans(*location)
+        .iter()
+        .any(|span| span.contains(dep_span))
+    })
+    .collect::<Vec<_>>();
+
+  let steps = targets
+    .iter()
+    .flat_map(|target| {
+      deps
+        .iter()
+        .filter_map(|dep| infoflow::explain_dependency(results, *target, *dep))
+    })
+    .min_by_key(|steps| steps.len())
+    .context("Selected code is not a dependency of the target")?;
+
+  let steps = steps
+    .into_iter()
+    .map(|step| ExplainStep {
+      place: step
+        .place
+        .to_string(tcx, body)
+        .unwrap_or_else(|| format!("{:?}", step.place)),
+      ranges: to_ranges(
+        Span::merge_overlaps(location_spans(step.location)),
+        source_map,
+      ),
+      reason: step.reason,
+    })
+    .collect();
+
+  Ok(ExplainOutput { steps })
+}
@@ -1,19 +1,19 @@
 use anyhow::Result;
-use flowistry::infoflow::{self, Direction};
+use flowistry::infoflow::{self, DependencyKind, Direction, FlowConfig};
 use itertools::Itertools;
 use rustc_hir::BodyId;
 use rustc_middle::ty::TyCtxt;
-use rustc_span::Span;
 use rustc_utils::{
   mir::borrowck_facts::get_body_with_borrowck_facts,
   source_map::{
     range::CharRange,
     spanner::{EnclosingHirSpans, Spanner},
   },
-  SpanExt,
 };
 use serde::Serialize;
 
+use crate::targets::{place_targets, to_ranges};
+
 mod direct_influence;
 
 #[derive(Debug, Serialize)]
@@ -28,13 +28,25 @@ pub struct PlaceInfo {
 pub struct FocusOutput {
   pub place_info: Vec<PlaceInfo>,
   pub containers: Vec<CharRange>,
+  /// Functions containing `unsafe` code that the slices depend on analyzing
+  pub unsafe_callees: Vec<String>,
+  /// Whether some calls could not be analyzed because their callee is in another crate
+  pub reached_library: bool,
+  /// Whether the analysis ran out of time, so the slices are over-approximated
+  pub degraded: bool,
 }
 
-pub fn focus(tcx: TyCtxt, body_id: BodyId) -> Result<FocusOutput> {
+pub fn focus(
+  tcx: TyCtxt,
+  body_id: BodyId,
+  config: &FlowConfig,
+  kind: DependencyKind,
+) -> Result<FocusOutput> {
   let def_id = tcx.hir().body_owner_def_id(body_id);
   let body_with_facts = get_body_with_borrowck_facts(tcx, def_id);
   let body = &body_with_facts.body;
-  let results = &infoflow::compute_flow(tcx, body_id, body_with_facts);
+  let results =
+    &infoflow::compute_flow_with_config(tcx, body_id, body_with_facts, config);
 
   let source_map = tcx.sess.source_map();
   let spanner = Spanner::new(tcx, body_id, body);
@@ -45,11 +57,7 @@ pub fn focus(tcx: TyCtxt, body_id: BodyId) -> Result<FocusOutput> {
     .map(|mir_span| {
       (
         mir_span.span,
-        mir_span
-          .locations
-          .iter()
-          .map(|location| (mir_span.place, *location))
-          .collect::<Vec<_>>(),
+        place_targets(mir_span.place, &mir_span.locations).collect::<Vec<_>>(),
       )
     })
     .into_group_map()
@@ -62,8 +70,13 @@ pub fn focus(tcx: TyCtxt, body_id: BodyId) -> Result<FocusOutput> {
     .map(|(_, target)| target.clone())
     .collect();
 
-  let relevant =
-    infoflow::compute_dependency_spans(results, targets, Direction::Both, &spanner);
+  let relevant = infoflow::compute_dependency_spans(
+    results,
+    targets,
+    Direction::Both,
+    &spanner,
+    kind,
+  )?;
 
   let direct =
     direct_influence::DirectInfluence::build(body, &results.analysis.place_info);
@@ -85,21 +98,13 @@ pub fn focus(tcx: TyCtxt, body_id: BodyId) -> Result<FocusOutput> {
 
       let slice = relevant;
 
-      let to_ranges = |v: Vec<Span>| {
-        v.into_iter()
-          .filter_map(|span| span.trim_leading_whitespace(source_map))
-          .flatten()
-          .filter_map(|span| CharRange::from_span(span, source_map).ok())
-          .collect::<Vec<_>>()
-      };
-
-      log::debug!("{:#?}", to_ranges(slice.clone()));
+      log::debug!("{:#?}", to_ranges(slice.clone(), source_map));
 
       Some(PlaceInfo {
         range: CharRange::from_span(mir_span.span(), source_map).ok()?,
-        ranges: to_ranges(vec![mir_span.span()]),
-        slice: to_ranges(slice),
-        direct_influence: to_ranges(direct_influence),
+        ranges: to_ranges(vec![mir_span.span()], source_map),
+        slice: to_ranges(slice, source_map),
+        direct_influence: to_ranges(direct_influence, source_map),
       })
     })
     .collect::<Vec<_>>();
@@ -118,8 +123,18 @@ pub fn focus(tcx: TyCtxt, body_id: BodyId) -> Result<FocusOutput> {
     containers.push(CharRange::from_span(sp, source_map)?);
   }
 
+  let diagnostics = results.analysis.diagnostics();
+  let unsafe_callees = diagnostics
+    .unsafe_callees
+    .into_iter()
+    .map(|def_id| tcx.def_path_str(def_id))
+    .collect();
+
   Ok(FocusOutput {
     place_info: slices,
     containers,
+    unsafe_callees,
+    reached_library: diagnostics.reached_library,
+    degraded: diagnostics.degraded,
   })
 }
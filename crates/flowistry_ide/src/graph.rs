@@ -1,24 +1,70 @@
-use flowistry::pdg::PdgParams;
+use std::{fs, str::FromStr, time::Duration};
+
+use flowistry::{infoflow::FlowConfig, pdg::PdgParams};
+use flowistry_pdg::{export, graph as portable};
 use rustc_utils::{mir::borrowck_facts, source_map::find_bodies::find_bodies};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::plugin::{start_time_limit, FlowistryError, FlowistryResult};
 
-use crate::plugin::FlowistryResult;
+/// The text format in which the PDG is rendered.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+pub enum GraphFormat {
+  Dot,
+  Json,
+  GraphMl,
+}
+
+impl FromStr for GraphFormat {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "dot" => Ok(Self::Dot),
+      "json" => Ok(Self::Json),
+      "graphml" => Ok(Self::GraphMl),
+      _ => Err(format!("Could not parse: {s}")),
+    }
+  }
+}
 
 #[derive(Serialize)]
-pub struct GraphOutput {}
+pub struct GraphOutput {
+  /// The PDG of the requested item.
+  graph: portable::DepGraph,
+
+  /// The label of each node in `graph`, containing its source snippet.
+  labels: Vec<String>,
+
+  /// The PDG rendered in the requested format.
+  rendered: String,
+}
 
 struct Callbacks {
   item_name: String,
+  config: FlowConfig,
+  time_limit: Option<Duration>,
+  format: GraphFormat,
   output: Option<FlowistryResult<GraphOutput>>,
 }
 
+/// Returns the first line of `snippet`, shortened for display within a node.
+fn summarize_snippet(snippet: &str) -> String {
+  const MAX_LEN: usize = 60;
+  let line = snippet.lines().next().unwrap_or_default().trim();
+  if line.chars().count() > MAX_LEN {
+    format!("{}...", line.chars().take(MAX_LEN).collect::<String>())
+  } else {
+    line.to_string()
+  }
+}
+
 impl rustc_driver::Callbacks for Callbacks {
   fn config(&mut self, config: &mut rustc_interface::Config) {
     borrowck_facts::enable_mir_simplification();
     config.override_queries = Some(borrowck_facts::override_queries);
   }
 
-  fn after_parsing<'tcx>(
+  fn after_expansion<'tcx>(
     &mut self,
     _compiler: &rustc_interface::interface::Compiler,
     queries: &'tcx rustc_interface::Queries<'tcx>,
@@ -34,29 +80,75 @@ impl rustc_driver::Callbacks for Callbacks {
             .then_some(def_id)
         })
         .collect::<Vec<_>>();
-      if defs.len() == 0 {
+      if defs.is_empty() {
         panic!("Could not find definition for: {}", self.item_name);
       } else if defs.len() > 1 {
         panic!("Ambiguous name. Found multiple definitions: {:?}", defs);
       }
 
       let def = *defs.first().unwrap();
-      let params = PdgParams::new(tcx, def);
+      let config = start_time_limit(&self.config, self.time_limit);
+      let params = PdgParams::new(tcx, def)
+        .with_context_mode(config.eval_mode.context_mode)
+        .with_config(config);
       let graph = flowistry::pdg::compute_pdg(params);
-      println!("PDG generated. Creating graphviz diagram at target/graph.pdf");
-      graph.generate_graphviz("target/graph.pdf").unwrap();
 
-      self.output = Some(Ok(GraphOutput {}))
+      let source_map = tcx.sess.source_map();
+      let labels = graph
+        .graph
+        .node_weights()
+        .map(|node| match source_map.span_to_snippet(node.span()) {
+          Ok(snippet) => format!("{node}\n{}", summarize_snippet(&snippet)),
+          Err(_) => node.to_string(),
+        })
+        .collect::<Vec<_>>();
+      let graph = graph.to_portable(tcx);
+
+      let label =
+        |index: portable::NodeIndex, _: &portable::DepNode| labels[index].clone();
+      let rendered = match self.format {
+        GraphFormat::Dot => export::to_dot(&graph, label),
+        GraphFormat::GraphMl => export::to_graphml(&graph, label),
+        GraphFormat::Json => serde_json::to_string_pretty(&graph).unwrap(),
+      };
+
+      self.output = Some(Ok(GraphOutput {
+        graph,
+        labels,
+        rendered,
+      }))
     });
     rustc_driver::Compilation::Stop
   }
 }
 
-pub fn graph(args: &[String], item_name: String) -> FlowistryResult<GraphOutput> {
+/// Computes the PDG of `item_name` and renders it in `format`.
+///
+/// The result is returned as a [`GraphOutput`]. If `output_path` is provided, then the
+/// rendered graph is also written to that file.
+pub fn graph(
+  args: &[String],
+  item_name: String,
+  format: GraphFormat,
+  output_path: Option<String>,
+  config: &FlowConfig,
+  time_limit: Option<Duration>,
+) -> FlowistryResult<GraphOutput> {
   let mut callbacks = Callbacks {
     item_name,
+    config: config.clone(),
+    time_limit,
+    format,
     output: None,
   };
   crate::plugin::run_with_callbacks(args, &mut callbacks)?;
-  callbacks.output.unwrap()
+  let output = callbacks.output.unwrap()?;
+
+  if let Some(path) = output_path {
+    fs::write(&path, &output.rendered).map_err(|e| FlowistryError::AnalysisError {
+      error: format!("Could not write graph to {path}: {e}"),
+    })?;
+  }
+
+  Ok(output)
 }
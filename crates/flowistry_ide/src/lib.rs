@@ -21,12 +21,16 @@ extern crate rustc_mir_dataflow;
 extern crate rustc_serialize;
 extern crate rustc_span;
 
+mod chop;
 #[cfg(feature = "decompose")]
 mod decompose;
+mod explain;
 mod focus;
 mod graph;
 mod playground;
 mod plugin;
+mod slice;
 mod spans;
+mod targets;
 
 pub use plugin::FlowistryPlugin;
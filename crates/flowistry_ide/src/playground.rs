@@ -1,4 +1,5 @@
 use anyhow::Result;
+use flowistry::infoflow::FlowConfig;
 use log::debug;
 use rustc_data_structures::fx::FxHashSet as HashSet;
 use rustc_hir::BodyId;
@@ -11,7 +12,11 @@ pub struct PlaygroundOutput {
   outlives: HashSet<(String, String)>,
 }
 
-pub fn playground(tcx: TyCtxt, body_id: BodyId) -> Result<PlaygroundOutput> {
+pub fn playground(
+  tcx: TyCtxt,
+  body_id: BodyId,
+  _config: &FlowConfig,
+) -> Result<PlaygroundOutput> {
   let def_id = tcx.hir().body_owner_def_id(body_id);
   let body_with_facts = get_body_with_borrowck_facts(tcx, def_id);
   let body = &body_with_facts.body;
@@ -3,16 +3,19 @@ use std::{
   env,
   path::PathBuf,
   process::{exit, Command},
-  time::Instant,
+  time::{Duration, Instant},
 };
 
 use anyhow::Context;
 use base64::Engine;
 use clap::{Parser, Subcommand};
-use flowistry::extensions::{
-  ContextMode, EvalMode, MutabilityMode, PointerMode, EVAL_MODE,
+use flowistry::{
+  extensions::{
+    ContextMode, CrossCrateMode, EvalMode, MutabilityMode, PointerMode, UnsafeMode,
+  },
+  infoflow::{summaries::CallSummaries, DependencyKind, FlowConfig, TrackedKinds},
+  mir::engine::EngineMode,
 };
-use fluid_let::fluid_set;
 use log::{debug, info};
 use rustc_hir::BodyId;
 use rustc_interface::interface::Result as RustcResult;
@@ -31,6 +34,8 @@ use rustc_utils::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::graph::GraphFormat;
+
 #[derive(Parser, Serialize, Deserialize)]
 pub struct FlowistryPluginArgs {
   #[clap(long)]
@@ -42,6 +47,20 @@ pub struct FlowistryPluginArgs {
   mutability_mode: Option<MutabilityMode>,
   #[clap(long)]
   pointer_mode: Option<PointerMode>,
+  #[clap(long)]
+  cross_crate_mode: Option<CrossCrateMode>,
+  #[clap(long)]
+  unsafe_mode: Option<UnsafeMode>,
+  /// A TOML file of flow summaries, used in addition to the bundled std summaries
+  #[clap(long)]
+  call_summaries: Option<PathBuf>,
+  /// How the analysis stores its results, to use less memory on large functions
+  #[clap(long)]
+  engine_mode: Option<EngineMode>,
+  /// Stops the analysis after this many seconds, returning an over-approximation
+  /// of the slices
+  #[clap(long)]
+  time_limit: Option<f64>,
 
   #[clap(subcommand)]
   command: FlowistryCommand,
@@ -57,11 +76,56 @@ enum FlowistryCommand {
     file: String,
     pos_line: usize,
     pos_column: usize,
+
+    /// One of `All`, `Data`, `Control` or `Thin`. A `Thin` slice only contains
+    /// the instructions that produced a value through data flow.
+    #[clap(long, default_value = "All")]
+    dependency_kind: DependencyKind,
+  },
+
+  /// Computes the backward slice of the place at the position, without analyzing
+  /// the code that the place does not depend on.
+  Slice {
+    file: String,
+    pos_line: usize,
+    pos_column: usize,
+
+    /// One of `All`, `Data`, `Control` or `Thin`.
+    #[clap(long, default_value = "All")]
+    dependency_kind: DependencyKind,
+  },
+
+  /// Computes the code on flows from the place at the source position
+  /// to the place at the sink position, which must be in the same function.
+  Chop {
+    file: String,
+    source_line: usize,
+    source_column: usize,
+    sink_line: usize,
+    sink_column: usize,
+  },
+
+  /// Explains why the code at the dependency position is in the backward slice
+  /// of the place at the target position, as a chain of steps.
+  Explain {
+    file: String,
+    target_line: usize,
+    target_column: usize,
+    dep_line: usize,
+    dep_column: usize,
   },
 
   Graph {
     file: String,
     item: String,
+
+    /// One of `dot`, `json` or `graphml`.
+    #[clap(long, default_value = "dot")]
+    format: GraphFormat,
+
+    /// If provided, the rendered graph is also written to this path.
+    #[clap(long)]
+    output: Option<String>,
   },
 
   Decompose {
@@ -122,6 +186,9 @@ impl RustcPlugin for FlowistryPlugin {
     let file = match &args.command {
       Spans { file, .. } => file,
       Focus { file, .. } => file,
+      Slice { file, .. } => file,
+      Chop { file, .. } => file,
+      Explain { file, .. } => file,
       Graph { file, .. } => file,
       Decompose { file, .. } => file,
       Playground { file, .. } => file,
@@ -145,8 +212,44 @@ impl RustcPlugin for FlowistryPlugin {
         .mutability_mode
         .unwrap_or(MutabilityMode::DistinguishMut),
       pointer_mode: plugin_args.pointer_mode.unwrap_or(PointerMode::Precise),
+      cross_crate_mode: plugin_args
+        .cross_crate_mode
+        .unwrap_or(CrossCrateMode::LocalOnly),
+      unsafe_mode: plugin_args.unsafe_mode.unwrap_or(UnsafeMode::Skip),
+    };
+
+    let mut summaries = CallSummaries::std();
+    if let Some(path) = &plugin_args.call_summaries {
+      match CallSummaries::load(path) {
+        Ok(user_summaries) => summaries.extend(user_summaries),
+        Err(e) => {
+          return postprocess::<()>(Err(FlowistryError::AnalysisError {
+            error: format!("{e:#}"),
+          }))
+        }
+      }
+    }
+
+    // The deadline is set when the analysis starts, see `start_time_limit`
+    let time_limit = match plugin_args
+      .time_limit
+      .map(Duration::try_from_secs_f64)
+      .transpose()
+    {
+      Ok(time_limit) => time_limit,
+      Err(e) => {
+        return postprocess::<()>(Err(FlowistryError::AnalysisError {
+          error: format!("Invalid time limit: {e}"),
+        }))
+      }
+    };
+
+    let config = FlowConfig {
+      eval_mode,
+      summaries,
+      engine_mode: plugin_args.engine_mode.unwrap_or_default(),
+      ..Default::default()
     };
-    fluid_set!(EVAL_MODE, eval_mode);
 
     use FlowistryCommand::*;
     match plugin_args.command {
@@ -174,12 +277,15 @@ impl RustcPlugin for FlowistryPlugin {
           crate::playground::playground,
           compute_target,
           &compiler_args,
+          &config,
+          time_limit,
         ))
       }
       Focus {
         file,
         pos_line,
         pos_column,
+        dependency_kind,
         ..
       } => {
         let compute_target = || {
@@ -195,9 +301,107 @@ impl RustcPlugin for FlowistryPlugin {
           debug!("eyo WTF {range:?} {file}");
           FunctionIdentifier::Range(range)
         };
-        postprocess(run(crate::focus::focus, compute_target, &compiler_args))
+        let config = FlowConfig {
+          tracked_kinds: TrackedKinds::for_kind(dependency_kind),
+          ..config
+        };
+        let focus = move |tcx: TyCtxt<'_>, body_id: BodyId, config: &FlowConfig| {
+          crate::focus::focus(tcx, body_id, config, dependency_kind)
+        };
+        postprocess(run(
+          focus,
+          compute_target,
+          &compiler_args,
+          &config,
+          time_limit,
+        ))
       }
-      Graph { item, .. } => postprocess(crate::graph::graph(&compiler_args, item)),
+      Slice {
+        file,
+        pos_line,
+        pos_column,
+        dependency_kind,
+      } => {
+        let point = || char_at(&file, pos_line, pos_column);
+        let compute_target = || FunctionIdentifier::Range(point());
+        let slice = |tcx: TyCtxt<'_>, body_id: BodyId, config: &FlowConfig| {
+          crate::slice::slice(tcx, body_id, config, point(), dependency_kind)
+        };
+        postprocess(run(
+          slice,
+          compute_target,
+          &compiler_args,
+          &config,
+          time_limit,
+        ))
+      }
+      Chop {
+        file,
+        source_line,
+        source_column,
+        sink_line,
+        sink_column,
+      } => {
+        let point = |line, column| char_at(&file, line, column);
+        let compute_target =
+          || FunctionIdentifier::Range(point(source_line, source_column));
+        let chop = |tcx: TyCtxt<'_>, body_id: BodyId, config: &FlowConfig| {
+          crate::chop::chop(
+            tcx,
+            body_id,
+            config,
+            point(source_line, source_column),
+            point(sink_line, sink_column),
+          )
+        };
+        postprocess(run(
+          chop,
+          compute_target,
+          &compiler_args,
+          &config,
+          time_limit,
+        ))
+      }
+      Explain {
+        file,
+        target_line,
+        target_column,
+        dep_line,
+        dep_column,
+      } => {
+        let point = |line, column| char_at(&file, line, column);
+        let compute_target =
+          || FunctionIdentifier::Range(point(target_line, target_column));
+        let explain = |tcx: TyCtxt<'_>, body_id: BodyId, config: &FlowConfig| {
+          crate::explain::explain(
+            tcx,
+            body_id,
+            config,
+            point(target_line, target_column),
+            point(dep_line, dep_column),
+          )
+        };
+        postprocess(run(
+          explain,
+          compute_target,
+          &compiler_args,
+          &config,
+          time_limit,
+        ))
+      }
+      Graph {
+        item,
+        format,
+        output,
+        ..
+      } => postprocess(crate::graph::graph(
+        &compiler_args,
+        item,
+        format,
+        output,
+        &config,
+        time_limit,
+      )),
       Decompose {
         file: _file,
         pos: _pos,
@@ -212,6 +416,8 @@ impl RustcPlugin for FlowistryPlugin {
               crate::decompose::decompose,
               id,
               &compiler_args,
+              &config,
+              time_limit,
             ))
           } else {
             panic!("Flowistry must be built with the decompose feature")
@@ -223,6 +429,19 @@ impl RustcPlugin for FlowistryPlugin {
   }
 }
 
+/// Returns the range of the character at a position, which unlike an empty range
+/// overlaps the spans of the places at that position.
+fn char_at(file: &str, line: usize, column: usize) -> CharRange {
+  CharRange {
+    start: CharPos { line, column },
+    end: CharPos {
+      line,
+      column: column + 1,
+    },
+    filename: Filename::intern(file),
+  }
+}
+
 fn postprocess<T: Serialize>(result: FlowistryResult<T>) -> RustcResult<()> {
   let result = match result {
     Ok(output) => Ok(output),
@@ -262,21 +481,37 @@ pub fn run_with_callbacks(
   compiler.run().map_err(FlowistryError::BuildError)
 }
 
+/// Returns `config` with a deadline `time_limit` from now, so that the time limit
+/// only counts the time spent by the analysis and not by rustc.
+pub(crate) fn start_time_limit(
+  config: &FlowConfig,
+  time_limit: Option<Duration>,
+) -> FlowConfig {
+  let mut config = config.clone();
+  if let Some(time_limit) = time_limit {
+    config.budget.deadline = Some(Instant::now() + time_limit);
+  }
+  config
+}
+
 fn run<A: FlowistryAnalysis, T: ToSpan>(
   analysis: A,
   compute_target: impl FnOnce() -> T + Send,
   args: &[String],
+  config: &FlowConfig,
+  time_limit: Option<Duration>,
 ) -> FlowistryResult<A::Output> {
   let mut callbacks = FlowistryCallbacks {
     analysis: Some(analysis),
     compute_target: Some(compute_target),
     output: None,
     rustc_start: Instant::now(),
-    eval_mode: EVAL_MODE.copied(),
+    config: config.clone(),
+    time_limit,
   };
 
   info!("Starting rustc analysis...");
-  debug!("Eval mode: {:?}", callbacks.eval_mode);
+  debug!("Eval mode: {:?}", config.eval_mode);
 
   run_with_callbacks(args, &mut callbacks)?;
 
@@ -300,19 +535,31 @@ pub type FlowistryResult<T> = Result<T, FlowistryError>;
 
 pub trait FlowistryAnalysis: Sized + Send + Sync {
   type Output: Serialize + Send + Sync;
-  fn analyze(&mut self, tcx: TyCtxt, id: BodyId) -> anyhow::Result<Self::Output>;
+  fn analyze(
+    &mut self,
+    tcx: TyCtxt,
+    id: BodyId,
+    config: &FlowConfig,
+  ) -> anyhow::Result<Self::Output>;
 }
 
 // Implement FlowistryAnalysis for all functions with a type signature that matches
 // FlowistryAnalysis::analyze
 impl<F, O> FlowistryAnalysis for F
 where
-  F: for<'tcx> Fn<(TyCtxt<'tcx>, BodyId), Output = anyhow::Result<O>> + Send + Sync,
+  F: for<'tcx, 'a> Fn<(TyCtxt<'tcx>, BodyId, &'a FlowConfig), Output = anyhow::Result<O>>
+    + Send
+    + Sync,
   O: Serialize + Send + Sync,
 {
   type Output = O;
-  fn analyze(&mut self, tcx: TyCtxt, id: BodyId) -> anyhow::Result<Self::Output> {
-    (self)(tcx, id)
+  fn analyze(
+    &mut self,
+    tcx: TyCtxt,
+    id: BodyId,
+    config: &FlowConfig,
+  ) -> anyhow::Result<Self::Output> {
+    (self)(tcx, id, config)
   }
 }
 
@@ -321,7 +568,8 @@ struct FlowistryCallbacks<A: FlowistryAnalysis, T: ToSpan, F: FnOnce() -> T> {
   compute_target: Option<F>,
   output: Option<anyhow::Result<A::Output>>,
   rustc_start: Instant,
-  eval_mode: Option<EvalMode>,
+  config: FlowConfig,
+  time_limit: Option<Duration>,
 }
 
 impl<A: FlowistryAnalysis, T: ToSpan, F: FnOnce() -> T> rustc_driver::Callbacks
@@ -338,7 +586,6 @@ impl<A: FlowistryAnalysis, T: ToSpan, F: FnOnce() -> T> rustc_driver::Callbacks
     queries: &'tcx rustc_interface::Queries<'tcx>,
   ) -> rustc_driver::Compilation {
     elapsed("rustc", self.rustc_start);
-    fluid_set!(EVAL_MODE, self.eval_mode.unwrap_or_default());
 
     let start = Instant::now();
     queries.global_ctxt().unwrap().enter(|tcx| {
@@ -349,7 +596,8 @@ impl<A: FlowistryAnalysis, T: ToSpan, F: FnOnce() -> T> rustc_driver::Callbacks
         debug!("target span: {target:?}");
         let mut bodies = find_enclosing_bodies(tcx, target);
         let body = bodies.next().context("Selection did not map to a body")?;
-        analysis.analyze(tcx, body)
+        let config = start_time_limit(&self.config, self.time_limit);
+        analysis.analyze(tcx, body, &config)
       })());
     });
 
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use flowistry::infoflow::{self, DependencyKind, FlowConfig};
+use rustc_hir::BodyId;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+use rustc_utils::{
+  mir::borrowck_facts::get_body_with_borrowck_facts,
+  source_map::{
+    range::{CharRange, ToSpan},
+    spanner::{EnclosingHirSpans, Spanner},
+  },
+  SpanExt,
+};
+use serde::Serialize;
+
+use crate::targets::{span_targets, to_ranges};
+
+#[derive(Debug, Serialize)]
+pub struct SliceOutput {
+  /// The places selected as targets of the slice
+  pub targets: Vec<CharRange>,
+  /// The code that the targets depend on
+  pub slice: Vec<CharRange>,
+}
+
+/// Computes the backward slice of the places at `target`, only analyzing the code
+/// that the places depend on.
+pub fn slice(
+  tcx: TyCtxt,
+  body_id: BodyId,
+  config: &FlowConfig,
+  target: CharRange,
+  kind: DependencyKind,
+) -> Result<SliceOutput> {
+  let def_id = tcx.hir().body_owner_def_id(body_id);
+  let body_with_facts = get_body_with_borrowck_facts(tcx, def_id);
+  let body = &body_with_facts.body;
+
+  let source_map = tcx.sess.source_map();
+  let spanner = Spanner::new(tcx, body_id, body);
+
+  let (target_spans, targets) = span_targets(&spanner, target.to_span(tcx)?);
+
+  let deps = infoflow::compute_backward_dependencies(
+    tcx,
+    body_id,
+    body_with_facts,
+    config,
+    vec![targets],
+    kind,
+  )
+  .remove(0);
+  let slice = Span::merge_overlaps(
+    deps
+      .iter()
+      .flat_map(|location| {
+        spanner.location_to_spans(*location, body, EnclosingHirSpans::OuterOnly)
+      })
+      .collect(),
+  );
+
+  Ok(SliceOutput {
+    targets: to_ranges(target_spans, source_map),
+    slice: to_ranges(slice, source_map),
+  })
+}
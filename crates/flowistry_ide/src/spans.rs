@@ -18,7 +18,7 @@ struct Callbacks {
 }
 
 impl rustc_driver::Callbacks for Callbacks {
-  fn after_parsing<'tcx>(
+  fn after_expansion<'tcx>(
     &mut self,
     compiler: &rustc_interface::interface::Compiler,
     queries: &'tcx rustc_interface::Queries<'tcx>,
@@ -27,7 +27,7 @@ impl rustc_driver::Callbacks for Callbacks {
       let spans = find_bodies(tcx).into_iter().map(|(span, _)| span);
 
       self.output = Some((|| {
-        let source_map = compiler.session().source_map();
+        let source_map = compiler.sess.source_map();
         let source_file = Filename::intern(&self.filename)
           .find_source_file(source_map)
           .map_err(|_| FlowistryError::FileNotFound)?;
@@ -35,7 +35,7 @@ impl rustc_driver::Callbacks for Callbacks {
         let spans = spans
           .into_iter()
           .filter(|span| {
-            source_map.lookup_source_file(span.lo()).name_hash == source_file.name_hash
+            source_map.lookup_source_file(span.lo()).stable_id == source_file.stable_id
           })
           .filter_map(|span| CharRange::from_span(span, source_map).ok())
           .collect::<Vec<_>>();
@@ -0,0 +1,45 @@
+//! Conversions between the source ranges used by the IDE and the places that a command targets.
+
+use rustc_middle::mir::Place;
+use rustc_span::{source_map::SourceMap, Span};
+use rustc_utils::{
+  mir::location_or_arg::LocationOrArg,
+  source_map::{range::CharRange, spanner::Spanner},
+  SpanExt,
+};
+
+/// A place at a location where it is used.
+pub type Target<'tcx> = (Place<'tcx>, LocationOrArg);
+
+/// Returns a target for `place` at each of its `locations`.
+pub fn place_targets<'a, 'tcx: 'a>(
+  place: Place<'tcx>,
+  locations: &'a [LocationOrArg],
+) -> impl Iterator<Item = Target<'tcx>> + 'a {
+  locations.iter().map(move |location| (place, *location))
+}
+
+/// Returns the spans of the places within `span`, along with the targets for every
+/// location where those places are used.
+pub fn span_targets<'tcx>(
+  spanner: &Spanner<'tcx>,
+  span: Span,
+) -> (Vec<Span>, Vec<Target<'tcx>>) {
+  let places = spanner.span_to_places(span);
+  let spans = places.iter().map(|mir_span| mir_span.span.span()).collect();
+  let targets = places
+    .iter()
+    .flat_map(|mir_span| place_targets(mir_span.place, &mir_span.locations))
+    .collect();
+  (spans, targets)
+}
+
+/// Converts `spans` to ranges, excluding the leading whitespace of each line.
+pub fn to_ranges(spans: Vec<Span>, source_map: &SourceMap) -> Vec<CharRange> {
+  spans
+    .into_iter()
+    .filter_map(|span| span.trim_leading_whitespace(source_map))
+    .flatten()
+    .filter_map(|span| CharRange::from_span(span, source_map).ok())
+    .collect()
+}
@@ -16,7 +16,7 @@ use rustc_mir_dataflow::JoinSemiLattice;
 use rustc_span::FileName;
 use rustc_trait_selection::infer::{InferCtxtExt, TyCtxtInferExt};
 use rustc_utils::{BodyExt, PlaceExt, SpanExt};
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use termcolor::{Buffer, Color, ColorSpec, WriteColor};
 
 fn implements_trait<'tcx>(
   tcx: TyCtxt<'tcx>,
@@ -38,7 +38,11 @@ pub enum IssueFound {
   No,
 }
 
-pub fn analyze(body_id: &BodyId, results: &FlowResults) -> Result<IssueFound> {
+pub fn analyze(
+  body_id: &BodyId,
+  results: &FlowResults,
+  report: &mut Buffer,
+) -> Result<IssueFound> {
   let tcx = results.analysis.tcx;
   let body = results.analysis.body;
   let def_id = tcx.hir().body_owner_def_id(*body_id).to_def_id();
@@ -99,7 +103,7 @@ pub fn analyze(body_id: &BodyId, results: &FlowResults) -> Result<IssueFound> {
 
   let final_state = body
     .all_returns()
-    .map(|location| results.state_at(location).clone())
+    .map(|location| (*results.shared_state_at(location)).clone())
     .reduce(|mut a, b| {
       a.join(&b);
       a
@@ -117,7 +121,6 @@ pub fn analyze(body_id: &BodyId, results: &FlowResults) -> Result<IssueFound> {
     }
   }
 
-  let mut stdout = StandardStream::stderr(ColorChoice::Auto);
   let mut black_spec = ColorSpec::new();
   black_spec.set_fg(Some(Color::Yellow));
   let mut red_spec = ColorSpec::new();
@@ -156,9 +159,9 @@ pub fn analyze(body_id: &BodyId, results: &FlowResults) -> Result<IssueFound> {
       None => "<in macro expansion>".to_owned(),
     };
 
-    stdout.set_color(&red_spec)?;
+    report.set_color(&red_spec)?;
     writeln!(
-      stdout,
+      report,
       "ERROR: insecure flow in {filename} from data at {src_span}:",
       filename = filename
         .local_path_if_available()
@@ -168,23 +171,23 @@ pub fn analyze(body_id: &BodyId, results: &FlowResults) -> Result<IssueFound> {
       src_span = span_range(src_span)
     )?;
 
-    stdout.set_color(&black_spec)?;
+    report.set_color(&black_spec)?;
     writeln!(
-      stdout,
+      report,
       "  {src_snippet}",
       src_snippet = span_contents(src_span)
     )?;
 
-    stdout.set_color(&red_spec)?;
+    report.set_color(&red_spec)?;
     writeln!(
-      stdout,
+      report,
       "to data at {dst_span}:",
       dst_span = span_range(dst_span)
     )?;
 
-    stdout.set_color(&black_spec)?;
+    report.set_color(&black_spec)?;
     writeln!(
-      stdout,
+      report,
       "  {dst_snippet}\n",
       dst_snippet = span_contents(dst_span)
     )?;
@@ -16,15 +16,12 @@ mod analysis;
 use std::{borrow::Cow, io::Write};
 
 use analysis::IssueFound;
-use flowistry::infoflow;
-use rustc_hir::{
-  intravisit::{self, Visitor},
-  BodyId,
-};
-use rustc_middle::{hir::nested_filter::OnlyBodies, ty::TyCtxt};
+use flowistry::infoflow::{self, FlowConfig};
 use rustc_plugin::{CrateFilter, RustcPlugin, RustcPluginArgs};
 use rustc_utils::mir::borrowck_facts;
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use termcolor::{
+  BufferWriter, Color, ColorChoice, ColorSpec, StandardStream, WriteColor,
+};
 pub struct IfcPlugin;
 
 impl RustcPlugin for IfcPlugin {
@@ -54,31 +51,6 @@ impl RustcPlugin for IfcPlugin {
   }
 }
 
-pub struct IfcVisitor<'tcx> {
-  tcx: TyCtxt<'tcx>,
-  issue_found: IssueFound,
-}
-
-impl<'tcx> Visitor<'tcx> for IfcVisitor<'tcx> {
-  type NestedFilter = OnlyBodies;
-
-  fn nested_visit_map(&mut self) -> Self::Map {
-    self.tcx.hir()
-  }
-
-  fn visit_nested_body(&mut self, body_id: BodyId) {
-    intravisit::walk_body(self, self.tcx.hir().body(body_id));
-
-    let tcx = self.tcx;
-    let local_def_id = tcx.hir().body_owner_def_id(body_id);
-    let body_with_facts = borrowck_facts::get_body_with_borrowck_facts(tcx, local_def_id);
-    let flow = &infoflow::compute_flow(tcx, body_id, body_with_facts);
-    if let IssueFound::Yes = analysis::analyze(&body_id, flow).unwrap() {
-      self.issue_found = IssueFound::Yes;
-    }
-  }
-}
-
 pub struct Callbacks;
 impl rustc_driver::Callbacks for Callbacks {
   fn config(&mut self, config: &mut rustc_interface::Config) {
@@ -86,19 +58,31 @@ impl rustc_driver::Callbacks for Callbacks {
     config.override_queries = Some(borrowck_facts::override_queries);
   }
 
-  fn after_parsing<'tcx>(
+  fn after_crate_root_parsing<'tcx>(
     &mut self,
     _compiler: &rustc_interface::interface::Compiler,
     queries: &'tcx rustc_interface::Queries<'tcx>,
   ) -> rustc_driver::Compilation {
     queries.global_ctxt().unwrap().enter(|tcx| {
-      let mut visitor = IfcVisitor {
-        tcx,
-        issue_found: IssueFound::No,
-      };
-      tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+      // Reports are buffered so that reports of bodies analyzed in parallel are not
+      // interleaved, and printed in a deterministic order
+      let stderr = BufferWriter::stderr(ColorChoice::Auto);
+      let results =
+        infoflow::compute_crate_flow(tcx, &FlowConfig::default(), |body_id, flow| {
+          let mut report = BufferWriter::stderr(ColorChoice::Auto).buffer();
+          let issue_found = analysis::analyze(&body_id, flow, &mut report).unwrap();
+          (issue_found, report)
+        });
+
+      let mut issue_found = IssueFound::No;
+      for (_, (body_issue_found, report)) in results {
+        stderr.print(&report).unwrap();
+        if let IssueFound::Yes = body_issue_found {
+          issue_found = IssueFound::Yes;
+        }
+      }
 
-      if let IssueFound::No = visitor.issue_found {
+      if let IssueFound::No = issue_found {
         let mut stdout = StandardStream::stderr(ColorChoice::Auto);
         let mut green_spec = ColorSpec::new();
         green_spec.set_fg(Some(Color::Green));
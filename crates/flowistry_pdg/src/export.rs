@@ -0,0 +1,97 @@
+//! Renders a [`DepGraph`] in text formats understood by graph tools.
+//!
+//! Every renderer takes a function that computes the label of each node, so callers
+//! can annotate nodes with information that is not stored in the graph (such as source snippets).
+
+use std::fmt::Write;
+
+use crate::graph::{DepGraph, DepNode, NodeIndex};
+
+/// Renders `graph` in the Graphviz DOT format.
+pub fn to_dot(graph: &DepGraph, label: impl Fn(NodeIndex, &DepNode) -> String) -> String {
+  let escape = |s: &str| {
+    s.replace('\\', "\\\\")
+      .replace('"', "\\\"")
+      .replace('\n', "\\n")
+  };
+
+  let mut dot = String::from("digraph {\n");
+  for node in graph.node_indices() {
+    writeln!(
+      dot,
+      "  {node} [label=\"{}\", fontname=\"Courier New\", shape=box];",
+      escape(&label(node, &graph[node]))
+    )
+    .unwrap();
+  }
+  for (src, dst, edge) in &graph.edges {
+    writeln!(
+      dot,
+      "  {src} -> {dst} [label=\"{}\", fontname=\"Courier New\"];",
+      escape(&edge.to_string())
+    )
+    .unwrap();
+  }
+  dot.push_str("}\n");
+  dot
+}
+
+/// Renders `graph` in the GraphML format.
+///
+/// Nodes have the attributes `label`, `place`, `kind`, `at` and `span`.
+/// Edges have the attributes `kind` and `at`.
+pub fn to_graphml(
+  graph: &DepGraph,
+  label: impl Fn(NodeIndex, &DepNode) -> String,
+) -> String {
+  let escape = |s: &str| {
+    s.replace('&', "&amp;")
+      .replace('<', "&lt;")
+      .replace('>', "&gt;")
+      .replace('"', "&quot;")
+      .replace('\'', "&apos;")
+  };
+
+  let mut xml = String::from(concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+    "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+    "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+    "  <key id=\"place\" for=\"node\" attr.name=\"place\" attr.type=\"string\"/>\n",
+    "  <key id=\"node_kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+    "  <key id=\"node_at\" for=\"node\" attr.name=\"at\" attr.type=\"string\"/>\n",
+    "  <key id=\"span\" for=\"node\" attr.name=\"span\" attr.type=\"string\"/>\n",
+    "  <key id=\"edge_kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+    "  <key id=\"edge_at\" for=\"edge\" attr.name=\"at\" attr.type=\"string\"/>\n",
+    "  <graph id=\"pdg\" edgedefault=\"directed\">\n",
+  ));
+  for index in graph.node_indices() {
+    let node = &graph[index];
+    writeln!(xml, "    <node id=\"n{index}\">").unwrap();
+    for (key, value) in [
+      ("label", label(index, node)),
+      ("place", node.place.to_string()),
+      ("node_kind", format!("{:?}", node.kind)),
+      ("node_at", node.at.to_string()),
+      ("span", node.span.to_string()),
+    ] {
+      writeln!(xml, "      <data key=\"{key}\">{}</data>", escape(&value)).unwrap();
+    }
+    xml.push_str("    </node>\n");
+  }
+  for (i, (src, dst, edge)) in graph.edges.iter().enumerate() {
+    writeln!(
+      xml,
+      "    <edge id=\"e{i}\" source=\"n{src}\" target=\"n{dst}\">"
+    )
+    .unwrap();
+    for (key, value) in [
+      ("edge_kind", format!("{:?}", edge.kind)),
+      ("edge_at", edge.at.to_string()),
+    ] {
+      writeln!(xml, "      <data key=\"{key}\">{}</data>", escape(&value)).unwrap();
+    }
+    xml.push_str("    </edge>\n");
+  }
+  xml.push_str("  </graph>\n</graphml>\n");
+  xml
+}
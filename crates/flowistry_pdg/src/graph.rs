@@ -0,0 +1,236 @@
+//! A portable representation of the PDG.
+//!
+//! Unlike the graph computed by Flowistry, these types do not refer to any rustc data structures
+//! (except through [proxies](crate::rustc_proxies) when the `rustc` feature is enabled),
+//! so a serialized [`DepGraph`] can be loaded and inspected on stable Rust.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  rustc_proxies::{Place, Span},
+  CallString, CallStringTable, StableIdCollision,
+};
+
+/// The role of a [`DepNode`]'s place within the body that contains it.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum DepNodeKind {
+  /// A place reachable from the argument with the given (zero-based) index.
+  Argument(usize),
+
+  /// A place reachable from the return place.
+  Return,
+
+  /// A place mutated by a function call, at the call site.
+  CallSite,
+
+  /// A place mutated by any other instruction.
+  Local,
+}
+
+/// A node in the program dependence graph.
+///
+/// Represents a place at a particular call-string.
+/// The place is in the body of the leaf of the call-string.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+pub struct DepNode {
+  /// A place in memory in the body of the leaf of [`DepNode::at`].
+  pub place: Place,
+
+  /// A source-level representation of the place (such as `x.0`), if one exists.
+  pub place_pretty: Option<String>,
+
+  /// The source code of the instruction (or the declaration of the place) that the node
+  /// represents.
+  pub span: Span,
+
+  /// The role of the place in its body.
+  pub kind: DepNodeKind,
+
+  /// The point in the execution of the program.
+  pub at: CallString,
+}
+
+impl fmt::Display for DepNode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.place_pretty {
+      Some(s) => s.fmt(f)?,
+      None => self.place.fmt(f)?,
+    };
+    write!(f, " @ {}", self.at)
+  }
+}
+
+/// A kind of edge in the program dependence graph.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum DepEdgeKind {
+  /// X is control-dependent on Y if the value of Y influences the execution
+  /// of statements that affect the value of X.
+  Control,
+
+  /// X is data-dependent on Y if the value of Y is an input to statements that affect
+  /// the value of X.
+  Data,
+}
+
+/// An edge in the program dependence graph.
+///
+/// Represents an operation that induces a dependency between places.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct DepEdge {
+  /// Either data or control.
+  pub kind: DepEdgeKind,
+
+  /// The location of the operation.
+  pub at: CallString,
+}
+
+impl DepEdge {
+  /// Constructs a data edge.
+  pub fn data(at: CallString) -> Self {
+    DepEdge {
+      kind: DepEdgeKind::Data,
+      at,
+    }
+  }
+
+  /// Constructs a control edge.
+  pub fn control(at: CallString) -> Self {
+    DepEdge {
+      kind: DepEdgeKind::Control,
+      at,
+    }
+  }
+}
+
+impl fmt::Display for DepEdge {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:?}\n@ {}", self.kind, self.at)
+  }
+}
+
+/// The index of a node in a [`DepGraph`].
+pub type NodeIndex = usize;
+
+/// A serializable program dependence graph.
+///
+/// Nodes are stored in a vector and identified by their [`NodeIndex`].
+/// Edges are stored as `(source, target, edge)` triples.
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DepGraph {
+  /// The nodes of the graph.
+  pub nodes: Vec<DepNode>,
+
+  /// The edges of the graph.
+  pub edges: Vec<(NodeIndex, NodeIndex, DepEdge)>,
+}
+
+impl DepGraph {
+  /// Constructs an empty [`DepGraph`].
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Checks that the endpoints of every edge are nodes of the graph.
+  ///
+  /// Graphs built with [`DepGraph::add_edge`] are always valid, but a deserialized
+  /// graph may not be.
+  pub fn validate(&self) -> Result<(), InvalidEdge> {
+    let node_count = self.nodes.len();
+    match self
+      .edges
+      .iter()
+      .position(|(src, dst, _)| *src >= node_count || *dst >= node_count)
+    {
+      Some(edge) => Err(InvalidEdge {
+        edge,
+        src: self.edges[edge].0,
+        dst: self.edges[edge].1,
+        node_count,
+      }),
+      None => Ok(()),
+    }
+  }
+
+  /// Adds a node to the graph, returning its index.
+  pub fn add_node(&mut self, node: DepNode) -> NodeIndex {
+    self.nodes.push(node);
+    self.nodes.len() - 1
+  }
+
+  /// Adds an edge from `src` to `dst` to the graph.
+  pub fn add_edge(&mut self, src: NodeIndex, dst: NodeIndex, edge: DepEdge) {
+    assert!(src < self.nodes.len() && dst < self.nodes.len());
+    self.edges.push((src, dst, edge));
+  }
+
+  /// Returns an iterator over the indices of all nodes in the graph.
+  pub fn node_indices(&self) -> impl Iterator<Item = NodeIndex> {
+    0 .. self.nodes.len()
+  }
+
+  /// Returns a table of the call strings of every node and edge in the graph,
+  /// indexed by their [`CallString::stable_id`].
+  ///
+  /// Returns an error if two of the call strings have the same identifier.
+  pub fn call_strings(&self) -> Result<CallStringTable, StableIdCollision> {
+    let nodes = self.nodes.iter().map(|node| node.at);
+    let edges = self.edges.iter().map(|(_, _, edge)| edge.at);
+    CallStringTable::from_call_strings(nodes.chain(edges))
+  }
+
+  /// Returns the edges whose source is `node`.
+  pub fn outgoing(&self, node: NodeIndex) -> impl Iterator<Item = (NodeIndex, &DepEdge)> {
+    self
+      .edges
+      .iter()
+      .filter(move |(src, _, _)| *src == node)
+      .map(|(_, dst, edge)| (*dst, edge))
+  }
+
+  /// Returns the edges whose target is `node`.
+  pub fn incoming(&self, node: NodeIndex) -> impl Iterator<Item = (NodeIndex, &DepEdge)> {
+    self
+      .edges
+      .iter()
+      .filter(move |(_, dst, _)| *dst == node)
+      .map(|(src, _, edge)| (*src, edge))
+  }
+}
+
+/// An edge of a [`DepGraph`] whose source or target is not a node of the graph.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct InvalidEdge {
+  /// The position of the edge in [`DepGraph::edges`].
+  pub edge: usize,
+
+  /// The source of the edge.
+  pub src: NodeIndex,
+
+  /// The target of the edge.
+  pub dst: NodeIndex,
+
+  /// The number of nodes in the graph.
+  pub node_count: usize,
+}
+
+impl fmt::Display for InvalidEdge {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "edge {} ({} -> {}) is out of range for a graph with {} nodes",
+      self.edge, self.src, self.dst, self.node_count
+    )
+  }
+}
+
+impl std::error::Error for InvalidEdge {}
+
+impl std::ops::Index<NodeIndex> for DepGraph {
+  type Output = DepNode;
+
+  fn index(&self, index: NodeIndex) -> &Self::Output {
+    &self.nodes[index]
+  }
+}
@@ -11,7 +11,10 @@ pub(crate) mod rustc {
   pub use middle::mir;
 }
 
+pub mod export;
+pub mod graph;
 mod pdg;
+pub mod query;
 #[cfg(feature = "rustc")]
 mod rustc_impls;
 pub mod rustc_portable;
@@ -1,6 +1,6 @@
 //! The representation of the PDG.
 
-use std::fmt;
+use std::{collections::HashMap, fmt};
 
 use internment::Intern;
 use serde::{Deserialize, Serialize};
@@ -84,8 +84,25 @@ pub struct GlobalLocation {
   pub location: RichLocation,
 }
 
-#[cfg(not(feature = "rustc"))]
+impl GlobalLocation {
+  /// Encodes the location as a sequence of integers that do not depend on the
+  /// process, for use in [`CallString::stable_id`].
+  fn stable_words(&self) -> [u32; 4] {
+    let function = self.function.local_def_index.as_u32();
+    match self.location {
+      RichLocation::Location(location) => [
+        function,
+        0,
+        location.block.as_u32(),
+        location.statement_index as u32,
+      ],
+      RichLocation::Start => [function, 1, 0, 0],
+      RichLocation::End => [function, 2, 0, 0],
+    }
+  }
+}
 
+#[cfg(not(feature = "rustc"))]
 impl fmt::Display for GlobalLocation {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     write!(f, "{:?}::{}", self.function, self.location)
@@ -137,6 +154,12 @@ impl CallString {
     CallString::new(string)
   }
 
+  /// Returns true if `prefix` is a prefix of this call string, i.e. if this call string
+  /// is at or below the location `prefix` in the call-graph.
+  pub fn starts_with(self, prefix: CallString) -> bool {
+    self.0.starts_with(&prefix.0)
+  }
+
   pub fn is_at_root(self) -> bool {
     self.0.len() == 1
   }
@@ -145,9 +168,23 @@ impl CallString {
     *self.0.first().unwrap()
   }
 
-  pub fn stable_id(self) -> usize {
-    let r: &'static Vec<GlobalLocation> = self.0.as_ref();
-    r as *const Vec<GlobalLocation> as usize
+  /// Returns an identifier for the call string that is derived only from its contents.
+  ///
+  /// Unlike the address of the interned call string, the identifier is the same across
+  /// processes and runs, so it can be used to compare or join serialized PDGs.
+  /// Use a [`CallStringTable`] to map identifiers back to call strings.
+  pub fn stable_id(self) -> u64 {
+    // 64-bit FNV-1a, which (unlike `DefaultHasher`) is guaranteed to be stable.
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    self
+      .0
+      .iter()
+      .flat_map(|loc| loc.stable_words())
+      .flat_map(u32::to_le_bytes)
+      .fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+      })
   }
 
   pub fn iter_from_root(&self) -> impl DoubleEndedIterator<Item = GlobalLocation> + '_ {
@@ -174,3 +211,90 @@ impl fmt::Display for CallString {
     Ok(())
   }
 }
+
+/// A reverse lookup table from [`CallString::stable_id`] to [`CallString`].
+///
+/// The table is serializable, so it can be saved alongside a PDG dump and used to resolve
+/// the identifiers from two different runs.
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CallStringTable(HashMap<u64, CallString>);
+
+impl CallStringTable {
+  /// Constructs an empty table.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Constructs a table from `call_strings`, failing on the first stable-ID collision.
+  pub fn from_call_strings(
+    call_strings: impl IntoIterator<Item = CallString>,
+  ) -> Result<Self, StableIdCollision> {
+    let mut table = CallStringTable::new();
+    for call_string in call_strings {
+      table.insert(call_string)?;
+    }
+    Ok(table)
+  }
+
+  /// Adds `call_string` to the table, returning its stable identifier.
+  ///
+  /// Returns an error if a different call string with the same identifier is already
+  /// in the table, in which case the table is unchanged.
+  pub fn insert(&mut self, call_string: CallString) -> Result<u64, StableIdCollision> {
+    let id = call_string.stable_id();
+    let existing = *self.0.entry(id).or_insert(call_string);
+    if existing != call_string {
+      return Err(StableIdCollision {
+        id,
+        existing,
+        new: call_string,
+      });
+    }
+    Ok(id)
+  }
+
+  /// Returns the call string with the identifier `id`, if it is in the table.
+  pub fn get(&self, id: u64) -> Option<CallString> {
+    self.0.get(&id).copied()
+  }
+
+  /// Returns the number of call strings in the table.
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  /// Returns true if the table contains no call strings.
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  /// Returns an iterator over the identifiers and call strings in the table.
+  pub fn iter(&self) -> impl Iterator<Item = (u64, CallString)> + '_ {
+    self.0.iter().map(|(id, call_string)| (*id, *call_string))
+  }
+}
+
+/// Two different call strings with the same [`CallString::stable_id`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct StableIdCollision {
+  /// The colliding identifier.
+  pub id: u64,
+
+  /// The call string that was already in the table.
+  pub existing: CallString,
+
+  /// The call string that could not be inserted.
+  pub new: CallString,
+}
+
+impl fmt::Display for StableIdCollision {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "stable id collision ({:x}) between {} and {}",
+      self.id, self.existing, self.new
+    )
+  }
+}
+
+impl std::error::Error for StableIdCollision {}
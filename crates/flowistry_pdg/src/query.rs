@@ -0,0 +1,221 @@
+//! Queries over a [`DepGraph`], usable without rustc.
+//!
+//! The main type is [`GraphQuery`], which indexes the edges of a graph once so that
+//! reachability, chop and path queries are linear in the size of the graph.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+  graph::{DepEdge, DepGraph, DepNode, InvalidEdge, NodeIndex},
+  rustc_portable::LocalDefId,
+  CallString,
+};
+
+/// The direction in which edges are followed.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Direction {
+  /// From sources to the nodes that depend on them.
+  Forward,
+
+  /// From sinks to the nodes that they depend on.
+  Backward,
+}
+
+/// An index over the edges of a [`DepGraph`] for answering queries.
+///
+/// Queries ignore the given nodes that are not in the graph, e.g. indices into another graph.
+pub struct GraphQuery<'g> {
+  graph: &'g DepGraph,
+  successors: Vec<Vec<NodeIndex>>,
+  predecessors: Vec<Vec<NodeIndex>>,
+}
+
+impl<'g> GraphQuery<'g> {
+  /// Indexes every edge of `graph`.
+  ///
+  /// Returns an error if an edge refers to a node that is not in the graph,
+  /// e.g. because the graph was deserialized from a malformed dump.
+  pub fn new(graph: &'g DepGraph) -> Result<Self, InvalidEdge> {
+    Self::with_edge_filter(graph, |_| true)
+  }
+
+  /// Indexes only the edges of `graph` that satisfy `filter`, e.g. to only
+  /// follow data dependencies.
+  ///
+  /// Returns an error under the same conditions as [`GraphQuery::new`].
+  pub fn with_edge_filter(
+    graph: &'g DepGraph,
+    filter: impl Fn(&DepEdge) -> bool,
+  ) -> Result<Self, InvalidEdge> {
+    graph.validate()?;
+    let mut successors = vec![Vec::new(); graph.nodes.len()];
+    let mut predecessors = vec![Vec::new(); graph.nodes.len()];
+    for (src, dst, edge) in &graph.edges {
+      if filter(edge) {
+        successors[*src].push(*dst);
+        predecessors[*dst].push(*src);
+      }
+    }
+    Ok(GraphQuery {
+      graph,
+      successors,
+      predecessors,
+    })
+  }
+
+  /// Returns the underlying graph.
+  pub fn graph(&self) -> &'g DepGraph {
+    self.graph
+  }
+
+  fn in_graph(
+    &self,
+    nodes: impl IntoIterator<Item = NodeIndex>,
+  ) -> impl Iterator<Item = NodeIndex> {
+    let num_nodes = self.graph.nodes.len();
+    nodes.into_iter().filter(move |node| *node < num_nodes)
+  }
+
+  fn neighbors(&self, node: NodeIndex, direction: Direction) -> &[NodeIndex] {
+    match direction {
+      Direction::Forward => &self.successors[node],
+      Direction::Backward => &self.predecessors[node],
+    }
+  }
+
+  /// Returns every node reachable from `roots` in the given `direction`,
+  /// including the roots themselves.
+  pub fn reachable(
+    &self,
+    roots: impl IntoIterator<Item = NodeIndex>,
+    direction: Direction,
+  ) -> HashSet<NodeIndex> {
+    let mut visited = HashSet::new();
+    let mut queue = self.in_graph(roots).collect::<Vec<_>>();
+    while let Some(node) = queue.pop() {
+      if visited.insert(node) {
+        queue.extend(self.neighbors(node, direction));
+      }
+    }
+    visited
+  }
+
+  /// Returns every node that depends on any of `sources`.
+  pub fn forward(
+    &self,
+    sources: impl IntoIterator<Item = NodeIndex>,
+  ) -> HashSet<NodeIndex> {
+    self.reachable(sources, Direction::Forward)
+  }
+
+  /// Returns every node that any of `sinks` depends on.
+  pub fn backward(
+    &self,
+    sinks: impl IntoIterator<Item = NodeIndex>,
+  ) -> HashSet<NodeIndex> {
+    self.reachable(sinks, Direction::Backward)
+  }
+
+  /// Returns true if any of `sources` reaches any of `sinks`.
+  pub fn connects(
+    &self,
+    sources: impl IntoIterator<Item = NodeIndex>,
+    sinks: impl IntoIterator<Item = NodeIndex>,
+  ) -> bool {
+    let reached = self.forward(sources);
+    sinks.into_iter().any(|sink| reached.contains(&sink))
+  }
+
+  /// Returns the chop between `sources` and `sinks`, i.e. every node that is on
+  /// some path from a source to a sink.
+  pub fn chop(
+    &self,
+    sources: impl IntoIterator<Item = NodeIndex>,
+    sinks: impl IntoIterator<Item = NodeIndex>,
+  ) -> HashSet<NodeIndex> {
+    let forward = self.forward(sources);
+    let backward = self.backward(sinks);
+    forward.intersection(&backward).copied().collect()
+  }
+
+  /// Returns a shortest path from any of `sources` to any of `sinks`, including both
+  /// endpoints, or `None` if no sink is reachable.
+  ///
+  /// The path can serve as a witness for why a sink depends on a source.
+  pub fn shortest_path(
+    &self,
+    sources: impl IntoIterator<Item = NodeIndex>,
+    sinks: impl IntoIterator<Item = NodeIndex>,
+  ) -> Option<Vec<NodeIndex>> {
+    let sinks = sinks.into_iter().collect::<HashSet<_>>();
+    let mut parent: Vec<Option<NodeIndex>> = vec![None; self.graph.nodes.len()];
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    for source in self.in_graph(sources) {
+      if visited.insert(source) {
+        queue.push_back(source);
+      }
+    }
+
+    while let Some(node) = queue.pop_front() {
+      if sinks.contains(&node) {
+        let mut path = vec![node];
+        let mut current = node;
+        while let Some(prev) = parent[current] {
+          path.push(prev);
+          current = prev;
+        }
+        path.reverse();
+        return Some(path);
+      }
+
+      for next in self.neighbors(node, Direction::Forward) {
+        if visited.insert(*next) {
+          parent[*next] = Some(node);
+          queue.push_back(*next);
+        }
+      }
+    }
+
+    None
+  }
+
+  /// Returns the nodes that satisfy `predicate`.
+  pub fn nodes_where(
+    &self,
+    predicate: impl Fn(&DepNode) -> bool + 'g,
+  ) -> impl Iterator<Item = NodeIndex> + 'g {
+    let graph = self.graph;
+    graph
+      .node_indices()
+      .filter(move |node| predicate(&graph[*node]))
+  }
+
+  /// Returns the nodes whose call string starts with `prefix`, i.e. the nodes at or
+  /// beneath the location `prefix` in the call-graph.
+  pub fn nodes_with_prefix(
+    &self,
+    prefix: CallString,
+  ) -> impl Iterator<Item = NodeIndex> + 'g {
+    self.nodes_where(move |node| node.at.starts_with(prefix))
+  }
+
+  /// Returns the nodes whose place is in the body of `function`.
+  pub fn nodes_in_function(
+    &self,
+    function: LocalDefId,
+  ) -> impl Iterator<Item = NodeIndex> + 'g {
+    self.nodes_where(move |node| node.at.leaf().function == function)
+  }
+
+  /// Returns the distinct call strings of `nodes`.
+  pub fn call_strings(
+    &self,
+    nodes: impl IntoIterator<Item = NodeIndex>,
+  ) -> HashSet<CallString> {
+    self
+      .in_graph(nodes)
+      .map(|node| self.graph[node].at)
+      .collect()
+  }
+}
@@ -3,7 +3,7 @@ use std::fmt;
 use super::rustc_proxies::*;
 use crate::{
   pdg::GlobalLocation,
-  rustc::{def_id, hir, middle::ty::tls, mir},
+  rustc::{def_id, hir, middle::ty::tls, mir, span},
 };
 
 pub fn bbref_to_u32(r: &mir::BasicBlock) -> u32 {
@@ -74,6 +74,79 @@ impl From<DefIndex> for def_id::DefIndex {
   }
 }
 
+pub fn local_as_u32(local: &mir::Local) -> u32 {
+  local.as_u32()
+}
+
+impl From<Local> for mir::Local {
+  fn from(proxy: Local) -> mir::Local {
+    mir::Local::from_u32(proxy.private)
+  }
+}
+
+/// Fails for projections that are not represented by [`PlaceElem`].
+impl TryFrom<mir::PlaceElem<'_>> for PlaceElem {
+  type Error = ();
+
+  fn try_from(elem: mir::PlaceElem<'_>) -> Result<PlaceElem, ()> {
+    Ok(match elem {
+      mir::ProjectionElem::Deref => PlaceElem::Deref,
+      mir::ProjectionElem::Field(field, _) => PlaceElem::Field(field.as_u32()),
+      mir::ProjectionElem::Index(local) => PlaceElem::Index(local),
+      mir::ProjectionElem::ConstantIndex {
+        offset,
+        min_length,
+        from_end,
+      } => PlaceElem::ConstantIndex {
+        offset,
+        min_length,
+        from_end,
+      },
+      mir::ProjectionElem::Subslice { from, to, from_end } => {
+        PlaceElem::Subslice { from, to, from_end }
+      }
+      mir::ProjectionElem::Downcast(name, variant) => {
+        PlaceElem::Downcast(name.map(|name| name.to_string()), variant.as_u32())
+      }
+      mir::ProjectionElem::OpaqueCast(_) | mir::ProjectionElem::Subtype(_) => {
+        return Err(());
+      }
+    })
+  }
+}
+
+impl From<mir::Place<'_>> for Place {
+  fn from(place: mir::Place<'_>) -> Place {
+    Place {
+      local: place.local,
+      projection: place
+        .projection
+        .iter()
+        .filter_map(|elem| PlaceElem::try_from(elem).ok())
+        .collect(),
+    }
+  }
+}
+
+impl Span {
+  /// Resolves `span` to its file and line/column range using `source_map`.
+  pub fn from_rustc(span: span::Span, source_map: &span::source_map::SourceMap) -> Span {
+    let lo = source_map.lookup_char_pos(span.lo());
+    let hi = source_map.lookup_char_pos(span.hi());
+    Span {
+      file: lo.file.name.prefer_local().to_string(),
+      start: LineColumn {
+        line: lo.line,
+        column: lo.col.0,
+      },
+      end: LineColumn {
+        line: hi.line,
+        column: hi.col.0,
+      },
+    }
+  }
+}
+
 impl fmt::Display for GlobalLocation {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     tls::with_opt(|opt_tcx| match opt_tcx {
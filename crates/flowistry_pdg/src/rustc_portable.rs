@@ -29,6 +29,7 @@ cfg_if::cfg_if! {
         pub type DefIndex = def_id::DefIndex;
         pub type LocalDefId = def_id::LocalDefId;
         pub type DefId = def_id::DefId;
+        pub type Local = mir::Local;
         pub type Place<'tcx> = mir::Place<'tcx>;
     } else {
         pub use crate::rustc_proxies::*;
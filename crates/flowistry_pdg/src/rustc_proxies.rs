@@ -3,6 +3,8 @@
 //! Each type has an identical set of fields to the corresponding Rustc type.
 //! Paralegal serializes the PDG into these types, which are read by downstream property checkers.
 
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "rustc")]
@@ -60,6 +62,10 @@ macro_rules! proxy_index {
                 pub fn index(self) -> usize {
                     self.private as usize
                 }
+
+                pub fn as_u32(self) -> u32 {
+                    self.private
+                }
             }
         )*
     }
@@ -76,7 +82,10 @@ proxy_index! {
     DefIndex("def_id::DefIndex") from "def_index_as_u32";
 
     /// Proxy for `hir::def_id::CrateNum`
-    CrateNum("hir::def_id::CrateNum") from "crate_num_as_u32"
+    CrateNum("hir::def_id::CrateNum") from "crate_num_as_u32";
+
+    /// Proxy for `mir::Local`
+    Local("mir::Local") from "local_as_u32"
 }
 
 proxy_struct! {
@@ -136,3 +145,135 @@ impl PartialOrd for HirId {
     Some(self.cmp(other))
   }
 }
+
+/// Proxy for `mir::ProjectionElem`.
+///
+/// Projections that only change the type of a place (`OpaqueCast` and `Subtype`)
+/// are not represented, since they do not change the memory that the place refers to.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+pub enum PlaceElem {
+  /// Dereference of a pointer, written `*p`.
+  Deref,
+
+  /// Access of the field with the given index, written `p.0`.
+  Field(u32),
+
+  /// Access of an array or slice at the index stored in a local, written `p[_1]`.
+  Index(
+    #[cfg_attr(feature = "rustc", serde(with = "Local"))] crate::rustc_portable::Local,
+  ),
+
+  /// Access of an array or slice at a constant index.
+  ConstantIndex {
+    offset: u64,
+    min_length: u64,
+    from_end: bool,
+  },
+
+  /// Access of a constant range of an array or slice.
+  Subslice { from: u64, to: u64, from_end: bool },
+
+  /// Access of an enum as a particular variant, with the name of the variant if known.
+  Downcast(Option<String>, u32),
+}
+
+/// Proxy for `mir::Place`: a local with a path of projections.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+pub struct Place {
+  /// The local that the place is rooted in.
+  #[cfg_attr(feature = "rustc", serde(with = "Local"))]
+  pub local: crate::rustc_portable::Local,
+
+  /// The projections applied to the local, in order.
+  pub projection: Vec<PlaceElem>,
+}
+
+/// Renders a place in the same syntax as the `Debug` representation of `mir::Place`.
+impl fmt::Display for Place {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for elem in self.projection.iter().rev() {
+      match elem {
+        PlaceElem::Deref => write!(f, "(*")?,
+        PlaceElem::Downcast(..) => write!(f, "(")?,
+        _ => {}
+      }
+    }
+
+    write!(f, "_{}", self.local.as_u32())?;
+
+    for elem in &self.projection {
+      match elem {
+        PlaceElem::Deref => write!(f, ")")?,
+        PlaceElem::Field(field) => write!(f, ".{field}")?,
+        PlaceElem::Index(local) => write!(f, "[_{}]", local.as_u32())?,
+        PlaceElem::ConstantIndex {
+          offset,
+          min_length,
+          from_end: false,
+        } => write!(f, "[{offset:?} of {min_length:?}]")?,
+        PlaceElem::ConstantIndex {
+          offset,
+          min_length,
+          from_end: true,
+        } => write!(f, "[-{offset:?} of {min_length:?}]")?,
+        PlaceElem::Subslice {
+          from,
+          to,
+          from_end: true,
+        } if *to == 0 => write!(f, "[{from:?}:]")?,
+        PlaceElem::Subslice {
+          from,
+          to,
+          from_end: true,
+        } => write!(f, "[{from:?}:-{to:?}]")?,
+        PlaceElem::Subslice {
+          from,
+          to,
+          from_end: false,
+        } => write!(f, "[{from:?}..{to:?}]")?,
+        PlaceElem::Downcast(Some(name), _) => write!(f, " as {name})")?,
+        PlaceElem::Downcast(None, index) => write!(f, " as variant#{index})")?,
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A line and column in a source file.
+///
+/// Lines are 1-based and columns are 0-based, counted in characters.
+#[derive(
+  PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Serialize, Deserialize,
+)]
+pub struct LineColumn {
+  pub line: usize,
+  pub column: usize,
+}
+
+/// Proxy for `span::Span`, resolved to a range of characters in a source file.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug, Serialize, Deserialize)]
+pub struct Span {
+  /// The path of the source file containing the span.
+  pub file: String,
+
+  /// The start of the span (inclusive).
+  pub start: LineColumn,
+
+  /// The end of the span (exclusive).
+  pub end: LineColumn,
+}
+
+impl fmt::Display for Span {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}:{}:{}: {}:{}",
+      self.file,
+      self.start.line,
+      self.start.column + 1,
+      self.end.line,
+      self.end.column + 1
+    )
+  }
+}
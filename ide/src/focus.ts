@@ -22,15 +22,29 @@ interface PlaceInfo {
 interface Focus {
   place_info: PlaceInfo[];
   containers: Range[];
+  unsafe_callees: string[];
+  reached_library: boolean;
+  degraded: boolean;
+}
+
+interface Chop {
+  sources: Range[];
+  sinks: Range[];
+  slice: Range[];
 }
 
 class FocusBodyState {
   mark: vscode.Selection | null;
+  chop: Chop | null;
+  // The selection that the chop was computed for
+  chop_selection: vscode.Selection | null;
   focus: Focus;
   places: RangeTree<PlaceInfo>;
 
   constructor(focus: Focus, doc: vscode.TextDocument) {
     this.mark = null;
+    this.chop = null;
+    this.chop_selection = null;
     this.focus = focus;
     this.places = new RangeTree(
       focus.place_info.map((info) => ({ range: info.range, value: info })),
@@ -88,7 +102,13 @@ class FocusBodyState {
   render = async (editor: vscode.TextEditor, select = false) => {
     let doc = editor.document;
     let { seeds, slice, direct_influence } =
-      this.find_slice_at_selection(editor);
+      this.chop !== null
+        ? {
+            seeds: this.chop.sources.concat(this.chop.sinks),
+            slice: this.chop.slice,
+            direct_influence: [],
+          }
+        : this.find_slice_at_selection(editor);
 
     if (seeds.length > 0) {
       if (select) {
@@ -164,7 +184,16 @@ class FocusDocumentState {
       return body_state_res;
     }
 
+    // A chop is only shown until the user moves their selection
     let body_state = body_state_res.value;
+    if (
+      body_state.chop_selection !== null &&
+      !body_state.chop_selection.isEqual(editor.selection)
+    ) {
+      body_state.chop = null;
+      body_state.chop_selection = null;
+    }
+
     body_state.render(editor);
     return ok(null);
   };
@@ -208,6 +237,8 @@ class FocusDocumentState {
     }
 
     body_state_res.value.mark = editor.selection;
+    body_state_res.value.chop = null;
+    body_state_res.value.chop_selection = null;
     return ok(null);
   };
 
@@ -222,6 +253,43 @@ class FocusDocumentState {
     }
 
     body_state_res.value.mark = null;
+    body_state_res.value.chop = null;
+    body_state_res.value.chop_selection = null;
+    return ok(null);
+  };
+
+  chop = async (editor: vscode.TextEditor): Promise<FlowistryResult<null>> => {
+    let body_state_res = await this.get_body_state(editor);
+    if (body_state_res === null) {
+      return ok(null);
+    } else if (!is_ok(body_state_res)) {
+      return body_state_res;
+    }
+
+    // The chop goes from the mark to the cursor, so it requires a mark
+    let body_state = body_state_res.value;
+    if (body_state.mark === null) {
+      await vscode.window.showErrorMessage(
+        "Flowistry: set a focus mark before showing flows from the mark to the cursor"
+      );
+      return ok(null);
+    }
+
+    let cmd = [
+      "chop",
+      editor.document.fileName,
+      body_state.mark.anchor.line.toString(),
+      body_state.mark.anchor.character.toString(),
+      editor.selection.anchor.line.toString(),
+      editor.selection.anchor.character.toString(),
+    ];
+    let chop_res = await globals.call_flowistry<Chop>(cmd);
+    if (!is_ok(chop_res)) {
+      return chop_res;
+    }
+
+    body_state.chop = chop_res.value;
+    body_state.chop_selection = editor.selection;
     return ok(null);
   };
 
@@ -396,6 +464,7 @@ export class FocusMode {
     ["focus_mark", this.focus_mark],
     ["focus_unmark", this.focus_unmark],
     ["focus_select", this.focus_select],
+    ["focus_chop", this.focus_chop],
   ];
 
   private focus_subcommand =
@@ -439,6 +508,9 @@ export class FocusMode {
   focus_select = this.focus_subcommand((editor, doc_state) =>
     doc_state.select(editor)
   );
+  focus_chop = this.focus_subcommand((editor, doc_state) =>
+    doc_state.chop(editor)
+  );
 
   focus = async () => {
     if (this.mode === "idle") {